@@ -0,0 +1,124 @@
+use crate::error::EvalError;
+use crate::identifier::Identifier;
+use crate::parsing::expression::Expression;
+use crate::parsing::function::Function;
+use crate::parsing::macro_call::{Macro, MacroArg};
+use crate::parsing::span::Span;
+use crate::parsing::value::Value;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::rc::Rc;
+
+// Instructions de la machine à pile (`vm`), produites par `compiler`.
+// Les variables locales sont désignées par leur emplacement dans l'appel en cours,
+// les variables globales par leur indice ; les autres opérandes sont des indices dans les tables du `Module`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    // Pile des opérandes
+    Const(usize),
+    Unit,
+    Pop,
+    PopN(usize),
+    // Variables
+    Load(usize),
+    Store(usize),
+    LoadGlobal(usize),
+    DeclareGlobal { global: usize, mutable: bool },
+    SetGlobal(usize),
+    // Opérations sur les entiers : chaque opérande est vérifié dès qu'il est évalué
+    CheckInt(usize),
+    Add,
+    Sub,
+    Mul,
+    Div(usize),
+    Mod(usize),
+    Lt,
+    Leq,
+    Gt,
+    Geq,
+    Eq { left: usize, right: usize, negate: bool },
+    Range { inclusive: bool },
+    // `&&` et `||` : le second opérande n'est évalué que si le premier ne suffit pas
+    AndLeft(usize),
+    AndRight,
+    OrLeft(usize),
+    OrRight,
+    // Sauts, vers l'indice d'une instruction
+    Jump(usize),
+    JumpIfFalse(usize, NotBool),
+    // `for` : le compteur et la borne sont rangés dans deux emplacements qui se suivent
+    ForRange { slot: usize, expr: usize },
+    ForNext { slot: usize, var: usize, exit: usize },
+    // Appel : la fonction, puis ses arguments, vérifiés un par un
+    Callee { argc: usize, callee: usize },
+    CheckArg { index: usize, expr: usize },
+    Call { argc: usize, call: usize },
+    Return,
+    // Macros, décrites dans la table `macros`
+    Macro(usize),
+    Assert { mac: usize, jump: usize },
+    AssertCmp { mac: usize, jump: usize },
+    // Erreur connue dès la compilation, levée si l'exécution arrive jusque-là
+    Fail(usize),
+}
+
+// Erreur d'une condition qui n'est pas un booléen, différente selon la construction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotBool {
+    Mismatch(usize),
+    Undefined(&'static str),
+}
+
+// Appel de macro : ses arguments à partir de la chaîne de format, les opérandes d'une assertion,
+// et les variables nommées dans la chaîne (`{x}`), chargées après les arguments
+#[derive(Debug, Clone)]
+pub struct MacroCall {
+    pub name: Macro,
+    pub args: Vec<MacroArg>,
+    pub operands: Vec<usize>,
+    pub implicit: Vec<Identifier>,
+}
+
+// Code d'une fonction, ou du programme lui-même
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    pub name: Identifier,
+    pub fun: Option<Rc<Function>>,
+    pub code: Vec<Op>,
+    // Portion du texte source de chaque instruction, pour situer les erreurs
+    pub spans: Vec<Option<Span>>,
+    pub locals: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Module {
+    // Le programme est le premier
+    pub chunks: Vec<Chunk>,
+    pub constants: Vec<Value>,
+    // Expressions citées par les erreurs, clonées seulement quand une erreur est levée
+    pub exprs: Vec<Expression>,
+    pub errors: Vec<EvalError>,
+    pub macros: Vec<MacroCall>,
+    pub globals: Vec<Identifier>,
+    // Code de chaque fonction compilée
+    pub functions: HashMap<*const Function, usize>,
+}
+
+impl Module {
+    pub fn chunk_of(&self, fun: &Rc<Function>) -> Option<usize> {
+        self.functions.get(&Rc::as_ptr(fun)).copied()
+    }
+}
+
+// Listing du code, une instruction par ligne
+impl Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for chunk in &self.chunks {
+            writeln!(f, "{} ({} local(s)):", chunk.name, chunk.locals)?;
+            for (ip, op) in chunk.code.iter().enumerate() {
+                writeln!(f, "  {:04} {:?}", ip, op)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use std::fmt::{self, Display};
+
+// Commandes de l'interpréteur, préfixées par `:`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Env,
+    Heap,
+    Type(String),
+    Reset,
+    Load(String),
+    // Schéma DOT de la mémoire, affiché ou écrit dans un fichier
+    Dot(Option<String>),
+    // État complet de la session, dans un fichier ou en mémoire
+    Save(String),
+    Restore(String),
+    Checkpoint,
+    Rollback,
+    // Parcours de l'historique des instructions de la boucle interactive
+    Undo,
+    Redo,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    Unknown(String),
+    MissingArgument(&'static str),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, CommandError> {
+        let line = line.trim();
+        let (name, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let arg = arg.trim();
+        match (name, arg) {
+            (":env", "") => Ok(Command::Env),
+            (":heap", "") => Ok(Command::Heap),
+            (":reset", "") => Ok(Command::Reset),
+            (":type", "") => Err(CommandError::MissingArgument(":type <expr>")),
+            (":type", expr) => Ok(Command::Type(expr.to_string())),
+            (":load", "") => Err(CommandError::MissingArgument(":load <file>")),
+            (":load", path) => Ok(Command::Load(path.to_string())),
+            (":dot", "") => Ok(Command::Dot(None)),
+            (":dot", path) => Ok(Command::Dot(Some(path.to_string()))),
+            (":save", "") => Err(CommandError::MissingArgument(":save <file>")),
+            (":save", path) => Ok(Command::Save(path.to_string())),
+            (":restore", "") => Err(CommandError::MissingArgument(":restore <file>")),
+            (":restore", path) => Ok(Command::Restore(path.to_string())),
+            (":checkpoint", "") => Ok(Command::Checkpoint),
+            (":rollback", "") => Ok(Command::Rollback),
+            (":undo", "") => Ok(Command::Undo),
+            (":redo", "") => Ok(Command::Redo),
+            _ => Err(CommandError::Unknown(line.to_string())),
+        }
+    }
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Unknown(line) => {
+                write!(f, "Unknown command `{}`. Available commands: :env, :heap, :type <expr>, :reset, :load <file>, :dot [file], :save <file>, :restore <file>, :checkpoint, :rollback, :undo, :redo", line)
+            },
+            CommandError::MissingArgument(usage) => write!(f, "Missing argument, usage: {}", usage),
+        }
+    }
+}
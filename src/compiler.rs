@@ -0,0 +1,605 @@
+use crate::bytecode::{Chunk, MacroCall, Module, NotBool, Op};
+use crate::error::EvalError;
+use crate::eval;
+use crate::identifier::Identifier;
+use crate::namespace::NameSpace;
+use crate::namespacestack::NameSpaceStack;
+use crate::parsing::binop::Binop;
+use crate::parsing::expression::Expression;
+use crate::parsing::function::Function;
+use crate::parsing::instruction::Instruction;
+use crate::parsing::leftexpression::LeftExpression;
+use crate::parsing::macro_call::{Macro, MacroArg};
+use crate::parsing::program::Program;
+use crate::parsing::span::Span;
+use crate::parsing::value::Value;
+use crate::r#type::Type;
+use crate::typing::{self, TypeEnv};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::rc::Rc;
+
+// Traduction d'un programme en code pour la machine à pile (`vm`).
+// Le code produit se comporte comme l'évaluateur de l'AST, qui reste la référence :
+// mêmes valeurs, mêmes sorties et mêmes erreurs, levées dans le même ordre.
+// Les constructions qui manipulent des adresses (pointeurs, structures, closures) ne sont pas encore traduites.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileError {
+    Unsupported(String),
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::Unsupported(what) => write!(f, "The bytecode VM does not support {} yet.", what),
+        }
+    }
+}
+
+impl CompileError {
+    // Nom de la variante, pour les sorties destinées aux programmes
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CompileError::Unsupported(_) => "Unsupported",
+        }
+    }
+}
+
+fn unsupported<T>(what: &str) -> Result<T, CompileError> {
+    Err(CompileError::Unsupported(what.to_string()))
+}
+
+struct Loop {
+    // Nombre d'opérandes sur la pile à l'entrée de la boucle
+    depth: usize,
+    next: usize,
+    breaks: Vec<usize>,
+}
+
+// Code d'une fonction en cours de compilation
+struct Builder {
+    chunk: Chunk,
+    main: bool,
+    scopes: Vec<HashMap<Identifier, (usize, bool)>>,
+    next_slot: usize,
+    loops: Vec<Loop>,
+    // Nombre d'opérandes sur la pile, connu à chaque instruction
+    depth: usize,
+    span: Option<Span>,
+    // Types connus des variables, pour vérifier les branches d'un `if` comme l'évaluateur
+    env: TypeEnv,
+}
+
+impl Builder {
+    fn new(name: Identifier, fun: Option<Rc<Function>>, env: TypeEnv) -> Self {
+        let main = fun.is_none();
+        Builder {
+            chunk: Chunk { name, fun, code: vec![], spans: vec![], locals: 0 },
+            main,
+            scopes: vec![],
+            next_slot: 0,
+            loops: vec![],
+            depth: 0,
+            span: None,
+            env,
+        }
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.depth = (self.depth as isize + effect(&op)) as usize;
+        self.chunk.code.push(op);
+        self.chunk.spans.push(self.span.clone());
+        self.chunk.code.len() - 1
+    }
+
+    fn here(&self) -> usize {
+        self.chunk.code.len()
+    }
+
+    // Complète un saut émis avant de connaître sa destination
+    fn patch(&mut self, at: usize) {
+        let target = self.here();
+        match &mut self.chunk.code[at] {
+            Op::Jump(t) | Op::JumpIfFalse(t, _) | Op::AndLeft(t) | Op::OrLeft(t) => *t = target,
+            Op::ForNext { exit, .. } => *exit = target,
+            Op::Assert { jump, .. } | Op::AssertCmp { jump, .. } => *jump = target,
+            op => panic!("cannot patch {:?}", op),
+        }
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+        self.env.push();
+    }
+
+    fn pop_scope(&mut self, first_slot: usize) {
+        self.scopes.pop();
+        self.env.pop();
+        self.next_slot = first_slot;
+    }
+
+    fn slot(&mut self) -> usize {
+        self.next_slot += 1;
+        self.chunk.locals = self.chunk.locals.max(self.next_slot);
+        self.next_slot - 1
+    }
+
+    // Les instructions du programme hors de tout bloc déclarent des variables globales
+    fn is_global_scope(&self) -> bool {
+        self.main && self.scopes.is_empty()
+    }
+}
+
+// Effet d'une instruction sur le nombre d'opérandes, quand l'exécution continue à l'instruction suivante
+fn effect(op: &Op) -> isize {
+    match op {
+        Op::Const(_) | Op::Unit | Op::Load(_) | Op::LoadGlobal(_) => 1,
+        Op::Pop | Op::Store(_) | Op::DeclareGlobal { .. } | Op::SetGlobal(_) => -1,
+        Op::PopN(n) => -(*n as isize),
+        Op::Add | Op::Sub | Op::Mul | Op::Div(_) | Op::Mod(_) | Op::Lt | Op::Leq | Op::Gt | Op::Geq
+        | Op::Eq { .. } | Op::Range { .. } | Op::AndRight => -1,
+        Op::OrLeft(_) | Op::JumpIfFalse(_, _) | Op::ForRange { .. } => -1,
+        Op::Call { argc, .. } => -(*argc as isize),
+        Op::CheckInt(_) | Op::AndLeft(_) | Op::OrRight | Op::Jump(_) | Op::ForNext { .. } | Op::Callee { .. }
+        | Op::CheckArg { .. } | Op::Return | Op::Assert { .. } | Op::AssertCmp { .. } | Op::Fail(_) => 0,
+        // Le nombre de valeurs prises par une macro est réglé par `Compiler::macro_call`
+        Op::Macro(_) => 0,
+    }
+}
+
+struct Compiler {
+    module: Module,
+    globals: HashMap<Identifier, usize>,
+    global_env: TypeEnv,
+    // NameSpaceStack vide : les types viennent seulement de `TypeEnv`
+    nss: NameSpaceStack,
+}
+
+pub fn compile(program: &Program) -> Result<Module, CompileError> {
+    let mut nss = NameSpaceStack::new();
+    nss.push(NameSpace::new());
+    let mut compiler = Compiler { module: Module::default(), globals: HashMap::new(), global_env: TypeEnv::new(), nss };
+    // Les variables globales sont connues d'avance, pour que les fonctions puissent y accéder
+    for instr in &program.0 {
+        match instr.unspanned() {
+            Instruction::Let { id, .. } => compiler.global(id),
+            Instruction::Fn(fun) => compiler.global(&fun.name),
+            _ => continue,
+        };
+    }
+    compiler.module.chunks.push(Builder::new(Identifier::from("main"), None, TypeEnv::new()).chunk);
+    let mut b = Builder::new(Identifier::from("main"), None, TypeEnv::new());
+    compiler.sequence(&mut b, &program.0)?;
+    b.emit(Op::Return);
+    compiler.module.chunks[0] = b.chunk;
+    Ok(compiler.module)
+}
+
+impl Compiler {
+    fn global(&mut self, id: &Identifier) -> usize {
+        if let Some(index) = self.globals.get(id) {
+            return *index;
+        }
+        self.module.globals.push(id.clone());
+        self.globals.insert(id.clone(), self.module.globals.len() - 1);
+        self.module.globals.len() - 1
+    }
+
+    fn constant(&mut self, b: &mut Builder, v: Value) {
+        self.module.constants.push(v);
+        b.emit(Op::Const(self.module.constants.len() - 1));
+    }
+
+    fn expr_index(&mut self, e: &Expression) -> usize {
+        self.module.exprs.push(e.clone());
+        self.module.exprs.len() - 1
+    }
+
+    // Erreur levée à l'exécution ; le code qui suit n'est jamais atteint
+    fn fail(&mut self, b: &mut Builder, err: EvalError) {
+        self.module.errors.push(err);
+        b.emit(Op::Fail(self.module.errors.len() - 1));
+    }
+
+    // Des instructions dont la dernière donne la valeur
+    fn sequence(&mut self, b: &mut Builder, instrs: &[Instruction]) -> Result<(), CompileError> {
+        if instrs.is_empty() {
+            b.emit(Op::Unit);
+        }
+        for (i, instr) in instrs.iter().enumerate() {
+            if i > 0 {
+                b.emit(Op::Pop);
+            }
+            // Comme l'interpréteur, les `if` d'une instruction du programme sont vérifiés avant son exécution
+            if b.is_global_scope() {
+                if let Err(err) = typing::check_branches(instr, &mut b.env.clone(), &self.nss) {
+                    self.fail(b, err);
+                }
+            }
+            self.instr(b, instr)?;
+        }
+        Ok(())
+    }
+
+    fn with_span<T>(&mut self, b: &mut Builder, span: &Span, f: impl FnOnce(&mut Self, &mut Builder) -> T) -> T {
+        let outer = b.span.replace(span.clone());
+        let res = f(self, b);
+        b.span = outer;
+        res
+    }
+
+    // Chaque instruction laisse exactement une valeur sur la pile
+    fn instr(&mut self, b: &mut Builder, instr: &Instruction) -> Result<(), CompileError> {
+        match instr {
+            Instruction::Spanned(span, instr) => self.with_span(b, span, |c, b| c.instr(b, instr)),
+            Instruction::Expr(e) => self.expr(b, e),
+            Instruction::Let { id, mutable, expr } => {
+                self.expr(b, expr)?;
+                let t = expr.type_of(&mut b.env, &self.nss).unwrap_or(Type::Infer);
+                self.declare(b, id, *mutable, t);
+                Ok(())
+            }
+            Instruction::Block { instrs, tail } => {
+                let first_slot = b.next_slot;
+                b.push_scope();
+                self.sequence(b, instrs)?;
+                if !tail {
+                    b.emit(Op::Pop);
+                    b.emit(Op::Unit);
+                }
+                b.pop_scope(first_slot);
+                Ok(())
+            }
+            Instruction::WriteAt(LeftExpression::Identifier(id), expr) => {
+                self.expr(b, expr)?;
+                match self.resolve(b, id) {
+                    Some(Variable::Local(slot, true)) => {
+                        b.emit(Op::Store(slot));
+                        b.emit(Op::Unit);
+                    },
+                    Some(Variable::Local(_, false)) => {
+                        self.fail(b, EvalError::NotMutable(Some(Expression::Identifier(id.clone()))));
+                    },
+                    Some(Variable::Global(global)) => {
+                        b.emit(Op::SetGlobal(global));
+                        b.emit(Op::Unit);
+                    },
+                    None => self.fail(b, EvalError::Undefined(id.clone())),
+                }
+                Ok(())
+            }
+            Instruction::WriteAt(_, _) => unsupported("writing through a pointer or a field"),
+            Instruction::While(cond, body) => {
+                let start = b.here();
+                b.loops.push(Loop { depth: b.depth, next: start, breaks: vec![] });
+                self.expr(b, cond)?;
+                let exit = b.emit(Op::JumpIfFalse(0, NotBool::Undefined("While")));
+                self.instr(b, body)?;
+                b.emit(Op::Pop);
+                b.emit(Op::Jump(start));
+                self.end_loop(b, exit);
+                Ok(())
+            }
+            Instruction::For(id, iter, body) => {
+                self.expr(b, iter)?;
+                let first_slot = b.next_slot;
+                let slot = b.slot();
+                b.slot();
+                let expr = self.expr_index(iter);
+                b.emit(Op::ForRange { slot, expr });
+                b.push_scope();
+                let var = b.slot();
+                b.scopes.last_mut().unwrap().insert(id.clone(), (var, false));
+                b.env.declare(id, Type::Int);
+                let next = b.emit(Op::ForNext { slot, var, exit: 0 });
+                b.loops.push(Loop { depth: b.depth, next, breaks: vec![] });
+                self.instr(b, body)?;
+                b.emit(Op::Pop);
+                b.emit(Op::Jump(next));
+                self.end_loop(b, next);
+                b.pop_scope(first_slot);
+                Ok(())
+            }
+            Instruction::Break | Instruction::Continue => {
+                let Some(l) = b.loops.last() else {
+                    return unsupported("`break` and `continue` outside of a loop");
+                };
+                let (depth, next) = (l.depth, l.next);
+                let extra = b.depth - depth;
+                if extra > 0 {
+                    b.emit(Op::PopN(extra));
+                }
+                let jump = b.emit(Op::Jump(next));
+                if matches!(instr, Instruction::Break) {
+                    b.loops.last_mut().unwrap().breaks.push(jump);
+                }
+                // Le code qui suit n'est pas atteint, mais garde la pile équilibrée
+                b.depth = depth + extra + 1;
+                Ok(())
+            }
+            Instruction::Fn(fun) => {
+                if fun.receiver.is_some() {
+                    return unsupported("methods");
+                }
+                self.function(fun)?;
+                self.constant(b, Value::Function(fun.clone()));
+                self.declare(b, &fun.name, false, fun.get_type(None));
+                Ok(())
+            }
+            Instruction::Free(_) => unsupported("`free`"),
+            Instruction::Struct(_) | Instruction::Impl(_, _) => unsupported("structures"),
+        }
+    }
+
+    // Sortie d'une boucle : les `break` sautent ici, où la boucle vaut ()
+    fn end_loop(&mut self, b: &mut Builder, exit: usize) {
+        let l = b.loops.pop().unwrap();
+        b.depth = l.depth;
+        b.patch(exit);
+        for jump in l.breaks {
+            b.patch(jump);
+        }
+        b.emit(Op::Unit);
+    }
+
+    // Déclare `id` avec la valeur au sommet de la pile, remplacée par (), la valeur de l'instruction
+    fn declare(&mut self, b: &mut Builder, id: &Identifier, mutable: bool, t: Type) {
+        b.env.declare(id, t.clone());
+        if b.is_global_scope() {
+            self.global_env.declare(id, t);
+            let global = self.global(id);
+            b.emit(Op::DeclareGlobal { global, mutable });
+            b.emit(Op::Unit);
+            return;
+        }
+        let scope = b.scopes.last().unwrap();
+        if scope.contains_key(id) {
+            self.fail(b, EvalError::AlreadyDefined(id.clone()));
+            return;
+        }
+        let slot = b.slot();
+        b.scopes.last_mut().unwrap().insert(id.clone(), (slot, mutable));
+        b.emit(Op::Store(slot));
+        b.emit(Op::Unit);
+    }
+
+    // Un appel ne voit que ses propres variables et les variables globales
+    fn resolve(&self, b: &Builder, id: &Identifier) -> Option<Variable> {
+        for scope in b.scopes.iter().rev() {
+            if let Some((slot, mutable)) = scope.get(id) {
+                return Some(Variable::Local(*slot, *mutable));
+            }
+        }
+        self.globals.get(id).map(|global| Variable::Global(*global))
+    }
+
+    fn function(&mut self, fun: &Rc<Function>) -> Result<(), CompileError> {
+        if self.module.chunk_of(fun).is_some() {
+            return Ok(());
+        }
+        let index = self.module.chunks.len();
+        self.module.functions.insert(Rc::as_ptr(fun), index);
+        self.module.chunks.push(Builder::new(fun.name.clone(), Some(fun.clone()), TypeEnv::new()).chunk);
+        let mut f = Builder::new(fun.name.clone(), Some(fun.clone()), self.global_env.clone());
+        f.push_scope();
+        for param in &fun.params {
+            if f.scopes[0].contains_key(&param.id) {
+                return unsupported("functions with two parameters of the same name");
+            }
+            let slot = f.slot();
+            f.scopes[0].insert(param.id.clone(), (slot, param.mutable));
+            f.env.declare(&param.id, param.ty.clone());
+        }
+        self.instr(&mut f, &fun.body)?;
+        f.emit(Op::Return);
+        self.module.chunks[index] = f.chunk;
+        Ok(())
+    }
+
+    fn load(&mut self, b: &mut Builder, id: &Identifier) {
+        match self.resolve(b, id) {
+            Some(Variable::Local(slot, _)) => {
+                b.emit(Op::Load(slot));
+            },
+            Some(Variable::Global(global)) => {
+                b.emit(Op::LoadGlobal(global));
+            },
+            None => {
+                self.fail(b, EvalError::Undefined(id.clone()));
+                b.emit(Op::Unit);
+            },
+        }
+    }
+
+    fn int_operand(&mut self, b: &mut Builder, e: &Expression) -> Result<(), CompileError> {
+        self.expr(b, e)?;
+        let expr = self.expr_index(e);
+        b.emit(Op::CheckInt(expr));
+        Ok(())
+    }
+
+    fn expr(&mut self, b: &mut Builder, e: &Expression) -> Result<(), CompileError> {
+        use Expression::*;
+        match e {
+            Spanned(span, e) => self.with_span(b, span, |c, b| c.expr(b, e)),
+            Const(v) => {
+                self.constant(b, Value::from(v.clone()));
+                Ok(())
+            }
+            Identifier(id) | ValueAt(LeftExpression::Identifier(id)) => {
+                self.load(b, id);
+                Ok(())
+            }
+            BinOp(e1, op, e2) => self.binop(b, e, e1, *op, e2),
+            Conditional { cond, cond_true, cond_false } => {
+                self.expr(b, cond)?;
+                let jump = b.emit(Op::JumpIfFalse(0, NotBool::Undefined("Conditional")));
+                let depth = b.depth;
+                self.expr(b, cond_true)?;
+                let end = b.emit(Op::Jump(0));
+                b.patch(jump);
+                b.depth = depth;
+                self.expr(b, cond_false)?;
+                b.patch(end);
+                Ok(())
+            }
+            If { cond, cond_true, cond_false } => {
+                self.expr(b, cond)?;
+                let expr = self.expr_index(cond);
+                let jump = b.emit(Op::JumpIfFalse(0, NotBool::Mismatch(expr)));
+                let depth = b.depth;
+                self.instr(b, cond_true)?;
+                match cond_false {
+                    // Sans `else`, un `if` vaut toujours ()
+                    None => {
+                        b.emit(Op::Pop);
+                        b.patch(jump);
+                        b.emit(Op::Unit);
+                    },
+                    Some(cond_false) => {
+                        let end = b.emit(Op::Jump(0));
+                        b.patch(jump);
+                        b.depth = depth;
+                        self.instr(b, cond_false)?;
+                        b.patch(end);
+                    },
+                }
+                Ok(())
+            }
+            Call(callee, args) => {
+                self.expr(b, callee)?;
+                let callee = self.expr_index(callee);
+                b.emit(Op::Callee { argc: args.len(), callee });
+                for (index, arg) in args.iter().enumerate() {
+                    self.expr(b, arg)?;
+                    let expr = self.expr_index(arg);
+                    b.emit(Op::CheckArg { index, expr });
+                }
+                let call = self.expr_index(e);
+                b.emit(Op::Call { argc: args.len(), call });
+                Ok(())
+            }
+            Macro(name, args) => self.macro_call(b, name, args),
+            NewPtr | Deref(_) | AmpersAnd(_) | ValueAt(_) => unsupported("pointers"),
+            StructLit(_, _) | Field(_, _) | Path(_, _) | MethodCall(_, _, _) => unsupported("structures"),
+            Closure { .. } => unsupported("closures"),
+        }
+    }
+
+    fn binop(&mut self, b: &mut Builder, e: &Expression, e1: &Expression, op: Binop, e2: &Expression) -> Result<(), CompileError> {
+        use Binop::*;
+        match op {
+            Add | Sub | Mul | Div | Mod | Leq | Geq | Lt | Gt | Range | RangeInclusive => {
+                self.int_operand(b, e1)?;
+                self.int_operand(b, e2)?;
+                b.emit(match op {
+                    Add => Op::Add,
+                    Sub => Op::Sub,
+                    Mul => Op::Mul,
+                    Div => Op::Div(self.expr_index(e2)),
+                    Mod => Op::Mod(self.expr_index(e)),
+                    Leq => Op::Leq,
+                    Geq => Op::Geq,
+                    Lt => Op::Lt,
+                    Gt => Op::Gt,
+                    Range => Op::Range { inclusive: false },
+                    _ => Op::Range { inclusive: true },
+                });
+            },
+            Eq | Neq => {
+                self.expr(b, e1)?;
+                self.expr(b, e2)?;
+                let (left, right) = (self.expr_index(e1), self.expr_index(e2));
+                b.emit(Op::Eq { left, right, negate: op == Neq });
+            },
+            And => {
+                self.expr(b, e1)?;
+                let jump = b.emit(Op::AndLeft(0));
+                self.expr(b, e2)?;
+                b.emit(Op::AndRight);
+                b.patch(jump);
+            },
+            Or => {
+                self.expr(b, e1)?;
+                let jump = b.emit(Op::OrLeft(0));
+                self.expr(b, e2)?;
+                b.emit(Op::OrRight);
+                b.patch(jump);
+            },
+        }
+        Ok(())
+    }
+
+    // Arguments d'un format, suivis des variables que la chaîne nomme sans les recevoir (`{x}`).
+    // Les erreurs qui ne dépendent que de la chaîne sont connues d'avance et levées après l'évaluation des arguments.
+    fn format_args(&mut self, b: &mut Builder, mac: usize, name: &Macro, args: &[MacroArg]) -> Result<(), CompileError> {
+        match eval::format_literal(name, args) {
+            Err(err) => {
+                self.fail(b, err);
+                return Ok(());
+            },
+            Ok(None) => return Ok(()),
+            Ok(Some(_)) => (),
+        }
+        for arg in &args[1..] {
+            self.expr(b, &arg.expr)?;
+        }
+        let values = vec![Value::Unit; args.len() - 1];
+        let mut implicit = vec![];
+        match eval::format_values(name, args, &values, &mut |id| {
+            implicit.push(id.clone());
+            Ok(Value::Unit)
+        }) {
+            Err(err) => self.fail(b, err),
+            Ok(_) => {
+                for id in &implicit {
+                    self.load(b, id);
+                }
+                self.module.macros[mac].implicit = implicit;
+            },
+        }
+        self.module.macros[mac].args = args.to_vec();
+        Ok(())
+    }
+
+    // Les opérandes d'une assertion restent sur la pile jusqu'à la macro, qui n'est atteinte que si l'assertion échoue
+    fn macro_call(&mut self, b: &mut Builder, name: &Macro, args: &[MacroArg]) -> Result<(), CompileError> {
+        let operands = match name {
+            Macro::Assert => 1,
+            Macro::AssertEq | Macro::AssertNe => 2,
+            _ => 0,
+        };
+        let depth = b.depth;
+        if args.len() < operands {
+            self.fail(b, EvalError::WrongArgumentCount{function: Identifier::from(name.to_string().as_str()), expected: operands, found: args.len()});
+            b.emit(Op::Unit);
+            return Ok(());
+        }
+        let (operand_args, rest) = args.split_at(operands);
+        let mut operand_exprs = vec![];
+        for arg in operand_args {
+            self.expr(b, &arg.expr)?;
+            operand_exprs.push(self.expr_index(&arg.expr));
+        }
+        let mac = self.module.macros.len();
+        self.module.macros.push(MacroCall { name: *name, args: vec![], operands: operand_exprs, implicit: vec![] });
+        let check = match name {
+            Macro::Assert => Some(b.emit(Op::Assert { mac, jump: 0 })),
+            Macro::AssertEq | Macro::AssertNe => Some(b.emit(Op::AssertCmp { mac, jump: 0 })),
+            _ => None,
+        };
+        // Le message d'une assertion n'est évalué que si elle échoue
+        self.format_args(b, mac, name, rest)?;
+        b.emit(Op::Macro(mac));
+        if let Some(check) = check {
+            b.patch(check);
+        }
+        b.depth = depth + 1;
+        Ok(())
+    }
+}
+
+enum Variable {
+    Local(usize, bool),
+    Global(usize),
+}
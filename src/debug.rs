@@ -0,0 +1,172 @@
+use crate::debugger::Hook;
+use crate::error::{Error, EvalError};
+use crate::namespacestack::NameSpaceStack;
+use crate::parsing::expression::Expression;
+use crate::parsing::instruction::Instruction;
+use crate::parsing::span::Span;
+use crate::parser::Parse;
+use crate::r#type::Type;
+use std::io::{BufRead, Write};
+
+// Commandes du débogueur, lues à chaque arrêt
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugCommand {
+    Step,
+    Next,
+    Continue,
+    Break(usize),
+    Print(String),
+    Backtrace,
+    Env,
+    Help,
+    Quit,
+}
+
+const HELP: &str = "\
+step (s)          run until the next instruction, entering function calls
+next (n)          run until the next instruction of this function or its callers
+continue (c)      run until a breakpoint
+break <line> (b)  stop before the instructions of a source line
+print <expr> (p)  evaluate an expression, `print *p` shows the value a pointer designates
+backtrace (bt)    show the function calls and the depth of the NameSpaceStack
+env               show the variables of every NameSpace
+quit (q)          stop the program";
+
+impl DebugCommand {
+    pub fn parse(line: &str) -> Result<DebugCommand, String> {
+        let line = line.trim();
+        let (name, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let arg = arg.trim();
+        match (name, arg) {
+            // Une ligne vide répète `step`, comme souvent dans les débogueurs
+            ("" | "step" | "s", "") => Ok(DebugCommand::Step),
+            ("next" | "n", "") => Ok(DebugCommand::Next),
+            ("continue" | "c", "") => Ok(DebugCommand::Continue),
+            ("break" | "b", line) => line.parse().map(DebugCommand::Break)
+                .map_err(|_| "Usage: break <line>".to_string()),
+            ("print" | "p", "") => Err("Usage: print <expr>".to_string()),
+            ("print" | "p", expr) => Ok(DebugCommand::Print(expr.to_string())),
+            ("backtrace" | "bt", "") => Ok(DebugCommand::Backtrace),
+            ("env", "") => Ok(DebugCommand::Env),
+            ("help" | "h", "") => Ok(DebugCommand::Help),
+            ("quit" | "q", "") => Ok(DebugCommand::Quit),
+            _ => Err(format!("Unknown command `{}`. Type `help` for the list of commands.", line)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Step,
+    // S'arrête dès que la profondeur d'appel revient à celle indiquée
+    Next(usize),
+    Continue,
+}
+
+pub struct Debugger<R: BufRead, W: Write> {
+    input: R,
+    out: W,
+    mode: Mode,
+    breakpoints: Vec<usize>,
+    // Instruction en cours dans chaque appel, de `main` à la fonction courante
+    current: Vec<Span>,
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    // L'exécution s'arrête avant la première instruction
+    pub fn new(input: R, out: W) -> Self {
+        Debugger { input, out, mode: Mode::Step, breakpoints: vec![], current: vec![] }
+    }
+
+    fn backtrace(&self, nss: &NameSpaceStack) -> String {
+        let frames = nss.frames();
+        let mut lines = vec![];
+        for depth in (0..self.current.len()).rev() {
+            let name = if depth == 0 { "main".to_string() } else { frames[depth - 1].name.to_string() };
+            let base = if depth == 0 { 0 } else { frames[depth - 1].base };
+            let end = frames.get(depth).map_or(nss.namespaces().len(), |frame| frame.base);
+            lines.push(format!("#{} {} at line {}, {} namespace(s)",
+                self.current.len() - 1 - depth, name, self.current[depth].line_col().0, end - base));
+        }
+        lines.push(format!("NameSpaceStack depth: {}", nss.namespaces().len()));
+        lines.join("\n")
+    }
+
+    fn print(&self, input: &str, nss: &mut NameSpaceStack) -> String {
+        let res = Expression::parse(input)
+            .map_err(Error::ParseError)
+            .and_then(|expr| expr.eval(nss).map_err(Error::EvalError));
+        match res {
+            Ok(v) => format!("{} : {} = {}", input, Type::from(&v), v),
+            Err(e) => e.report(),
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Hook for Debugger<R, W> {
+    fn before(&mut self, _instr: &Instruction, span: &Span, nss: &mut NameSpaceStack) -> Result<(), EvalError> {
+        let depth = nss.frames().len();
+        self.current.truncate(depth);
+        self.current.push(span.clone());
+        let line = span.line_col().0;
+        let stop = match self.mode {
+            Mode::Step => true,
+            Mode::Next(d) => depth <= d,
+            Mode::Continue => false,
+        };
+        if !stop && !self.breakpoints.contains(&line) {
+            return Ok(());
+        }
+        writeln!(self.out, "Stopped before line {}\n{}", line, span.snippet()).unwrap();
+        loop {
+            write!(self.out, "(debug) ").unwrap();
+            self.out.flush().unwrap();
+            let mut input = String::new();
+            // Sans commande à lire, le programme termine sans s'arrêter
+            if self.input.read_line(&mut input).unwrap_or(0) == 0 {
+                writeln!(self.out).unwrap();
+                self.mode = Mode::Continue;
+                self.breakpoints.clear();
+                return Ok(());
+            }
+            let output = match DebugCommand::parse(&input) {
+                Ok(DebugCommand::Step) => {
+                    self.mode = Mode::Step;
+                    return Ok(());
+                },
+                Ok(DebugCommand::Next) => {
+                    self.mode = Mode::Next(depth);
+                    return Ok(());
+                },
+                Ok(DebugCommand::Continue) => {
+                    self.mode = Mode::Continue;
+                    return Ok(());
+                },
+                Ok(DebugCommand::Break(line)) => {
+                    if !self.breakpoints.contains(&line) {
+                        self.breakpoints.push(line);
+                    }
+                    format!("Breakpoint at line {}", line)
+                },
+                Ok(DebugCommand::Print(expr)) => self.print(&expr, nss),
+                Ok(DebugCommand::Backtrace) => self.backtrace(nss),
+                Ok(DebugCommand::Env) => nss.to_string(),
+                Ok(DebugCommand::Help) => HELP.to_string(),
+                Ok(DebugCommand::Quit) => std::process::exit(0),
+                Err(e) => e,
+            };
+            writeln!(self.out, "{}", output).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(DebugCommand::parse("p  *ptr "), Ok(DebugCommand::Print("*ptr".to_string())));
+        assert_eq!(DebugCommand::parse(""), Ok(DebugCommand::Step));
+    }
+}
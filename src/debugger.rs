@@ -0,0 +1,39 @@
+use crate::error::EvalError;
+use crate::namespacestack::NameSpaceStack;
+use crate::parsing::instruction::Instruction;
+use crate::parsing::span::Span;
+use std::cell::RefCell;
+
+// Point d'arrêt de l'exécution, consulté avant chaque instruction du texte source.
+// Un débogueur peut y lire et modifier l'état, ou arrêter l'exécution en renvoyant une erreur.
+pub trait Hook {
+    fn before(&mut self, instr: &Instruction, span: &Span, nss: &mut NameSpaceStack) -> Result<(), EvalError>;
+}
+
+thread_local! {
+    static HOOK: RefCell<Option<Box<dyn Hook>>> = RefCell::new(None);
+}
+
+pub fn attach(hook: Box<dyn Hook>) {
+    HOOK.with(|h| *h.borrow_mut() = Some(hook));
+}
+
+pub fn detach() -> Option<Box<dyn Hook>> {
+    HOOK.with(|h| h.borrow_mut().take())
+}
+
+// Le crochet est retiré pendant son appel : les expressions qu'il évalue ne l'appellent pas à nouveau
+pub(crate) fn before(instr: &Instruction, span: &Span, nss: &mut NameSpaceStack) -> Result<(), EvalError> {
+    let Some(mut hook) = HOOK.with(|h| h.borrow_mut().take()) else {
+        return Ok(());
+    };
+    let res = hook.before(instr, span, nss);
+    HOOK.with(|h| {
+        let mut h = h.borrow_mut();
+        // Remis en place, sauf si un autre crochet a été installé entre-temps
+        if h.is_none() {
+            *h = Some(hook);
+        }
+    });
+    res
+}
@@ -0,0 +1,170 @@
+use crate::heap::Heap;
+use crate::memory::Address;
+use crate::memorycell::MemoryCell;
+use crate::namespacestack::NameSpaceStack;
+use crate::parsing::value::Value;
+use std::fmt::Write;
+
+// Schéma de la mémoire au format DOT de Graphviz (`dot -Tsvg`) : un cadre par appel de fonction,
+// qui contient une boîte par NameSpace, les cellules du tas à part, et une flèche par pointeur
+
+// Les valeurs sont affichées dans des étiquettes HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn node(addr: &Address) -> String {
+    match addr {
+        Address::StackAddress(index, id) => format!("ns{}:{}", index, id),
+        Address::HeapAddress(offset) => format!("heap{}", offset),
+    }
+}
+
+// Adresses désignées par une valeur, y compris dans les champs d'une structure
+fn pointers<'a>(value: &'a Value, found: &mut Vec<&'a Address>) {
+    match value {
+        Value::Pointer(addr) => found.push(addr),
+        Value::Struct(_, fields) => fields.iter().for_each(|(_, v)| pointers(v, found)),
+        _ => (),
+    }
+}
+
+fn exists(addr: &Address, nss: &NameSpaceStack, heap: &Heap) -> bool {
+    match addr {
+        Address::StackAddress(index, id) => nss.namespaces().get(*index).is_some_and(|ns| ns.contains(id)),
+        Address::HeapAddress(offset) => *offset < heap.cells().len(),
+    }
+}
+
+pub fn render(nss: &NameSpaceStack, heap: &Heap) -> String {
+    let mut dot = String::new();
+    let mut edges = vec![];
+    writeln!(dot, "digraph memory {{").unwrap();
+    writeln!(dot, "  rankdir=LR;").unwrap();
+    writeln!(dot, "  node [shape=plaintext, fontname=\"monospace\"];").unwrap();
+
+    writeln!(dot, "  subgraph cluster_stack {{").unwrap();
+    writeln!(dot, "    label=\"Stack\";").unwrap();
+    let frames = nss.frames();
+    let namespaces = nss.namespaces();
+    for frame in 0..=frames.len() {
+        let (name, start) = if frame == 0 {
+            ("main".to_string(), 0)
+        } else {
+            (frames[frame - 1].name.to_string(), frames[frame - 1].base)
+        };
+        let end = frames.get(frame).map_or(namespaces.len(), |f| f.base);
+        writeln!(dot, "    subgraph cluster_frame{} {{", frame).unwrap();
+        writeln!(dot, "      label=\"{}\";", name).unwrap();
+        for (index, ns) in namespaces.iter().enumerate().take(end).skip(start) {
+            let mut rows = vec![];
+            for (id, cell) in ns.cells() {
+                let mutable = if cell.is_mutable() { "mut " } else { "" };
+                rows.push(format!("<tr><td port=\"{}\" align=\"left\">{}{} : {}</td></tr>", id, mutable, id, escape(&cell.to_string())));
+                if let Ok(value) = cell.get_value() {
+                    let mut found = vec![];
+                    pointers(value, &mut found);
+                    for addr in found.into_iter().filter(|addr| exists(addr, nss, heap)) {
+                        edges.push(format!("ns{}:{} -> {};", index, id, node(addr)));
+                    }
+                }
+            }
+            if rows.is_empty() {
+                rows.push("<tr><td>(empty)</td></tr>".to_string());
+            }
+            writeln!(dot, "      ns{} [label=<<table border=\"0\" cellborder=\"1\" cellspacing=\"0\"><tr><td><b>[{}]</b></td></tr>{}</table>>];",
+                index, index, rows.join("")).unwrap();
+        }
+        writeln!(dot, "    }}").unwrap();
+    }
+    writeln!(dot, "  }}").unwrap();
+
+    writeln!(dot, "  subgraph cluster_heap {{").unwrap();
+    writeln!(dot, "    label=\"Heap\";").unwrap();
+    for (offset, cell) in heap.cells().iter().enumerate() {
+        let addr = Address::HeapAddress(offset);
+        match cell {
+            // Une cellule libérée reste dessinée, en gris
+            MemoryCell::NotAllocated => {
+                writeln!(dot, "    heap{} [shape=box, style=filled, fillcolor=lightgrey, fontcolor=grey40, color=grey, label=\"{} (freed)\"];",
+                    offset, addr).unwrap();
+            },
+            MemoryCell::AllocatedCell(_) => {
+                writeln!(dot, "    heap{} [shape=box, label=<{} : {}>];", offset, addr, escape(&cell.to_string())).unwrap();
+                if let Ok(value) = cell.get_value() {
+                    let mut found = vec![];
+                    pointers(value, &mut found);
+                    for target in found.into_iter().filter(|target| exists(target, nss, heap)) {
+                        edges.push(format!("heap{} -> {};", offset, node(target)));
+                    }
+                }
+            },
+        }
+    }
+    writeln!(dot, "  }}").unwrap();
+
+    for edge in edges {
+        writeln!(dot, "  {}", edge).unwrap();
+    }
+    writeln!(dot, "}}").unwrap();
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debugger::{self, Hook};
+    use crate::error::EvalError;
+    use crate::identifier::Identifier;
+    use crate::interpreter::Interpreter;
+    use crate::namespace::NameSpace;
+    use crate::parsing::instruction::Instruction;
+    use crate::parsing::span::Span;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // Schémas de la mémoire pris à chaque instruction exécutée dans une fonction
+    struct DotHook(Rc<RefCell<Vec<String>>>);
+
+    impl Hook for DotHook {
+        fn before(&mut self, _instr: &Instruction, _span: &Span, nss: &mut NameSpaceStack) -> Result<(), EvalError> {
+            if !nss.frames().is_empty() {
+                self.0.borrow_mut().push(render(nss, &Heap::new()));
+            }
+            Ok(())
+        }
+    }
+
+    // Un cadre par appel de fonction
+    #[test]
+    fn test_frames() {
+        let graphs = Rc::new(RefCell::new(vec![]));
+        debugger::attach(Box::new(DotHook(graphs.clone())));
+        let res = Interpreter::new().eval_str("fn f(n: isize) -> isize {\n    let r = &n;\n    *r\n}\nf(3)");
+        debugger::detach();
+        assert_eq!(res, Ok((None, Value::Integer(3))));
+        let graph = graphs.borrow().last().unwrap().clone();
+        assert!(graph.contains("subgraph cluster_frame1 {\n      label=\"f\";\n      ns1 ["));
+        assert!(graph.contains("n : isize = 3"));
+        assert!(graph.contains("ns2:r -> ns1:n;"));
+        assert!(graph.contains("f : fn(isize) -&gt; isize = &lt;fn f&gt;"));
+    }
+
+    // Les cellules libérées du tas sont grisées
+    #[test]
+    fn test_freed_cells() {
+        let mut heap = Heap::new();
+        let cell = heap.malloc(true, Value::Integer(5));
+        let ptr = heap.malloc(false, Value::Pointer(cell.clone()));
+        heap.free(&cell).unwrap();
+        assert!(heap.free(&cell).is_err());
+        let mut nss = NameSpaceStack::new();
+        nss.push(NameSpace::new());
+        nss.declare(&Identifier::from("h"), false, Value::Pointer(ptr)).unwrap();
+        let graph = render(&nss, &heap);
+        assert!(graph.contains("heap0 [shape=box, style=filled, fillcolor=lightgrey, fontcolor=grey40, color=grey, label=\"@[0] (freed)\"];"));
+        assert!(graph.contains("heap1 [shape=box, label=<@[1] : Ptr = @[0]>];"));
+        assert!(graph.contains("  ns0:h -> heap1;\n"));
+        assert!(graph.contains("  heap1 -> heap0;\n"));
+    }
+}
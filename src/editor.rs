@@ -0,0 +1,96 @@
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::path::PathBuf;
+
+const GRAMMAR: &str = include_str!("parsing/grammar.pest");
+const COMMANDS: [&str; 12] = [":env", ":heap", ":type", ":reset", ":load", ":dot", ":save", ":restore", ":checkpoint", ":rollback", ":undo", ":redo"];
+
+// Mots-clés de la grammaire : règles en majuscules réduites à un mot, comme `LET = _{ "let" }`
+fn keywords() -> Vec<String> {
+    GRAMMAR.lines().filter_map(|line| {
+        let (name, rule) = line.split_once('=')?;
+        if !name.trim().chars().all(|c| c.is_ascii_uppercase()) {
+            return None;
+        }
+        let word = rule.split('"').nth(1)?;
+        word.chars().all(|c| c.is_ascii_alphabetic()).then(|| word.to_string())
+    }).collect()
+}
+
+// Complétion des mots-clés, des identifiants liés et des commandes `:`
+pub struct MicroRustHelper {
+    keywords: Vec<String>,
+    identifiers: Vec<String>,
+}
+
+impl MicroRustHelper {
+    pub fn new() -> Self {
+        MicroRustHelper { keywords: keywords(), identifiers: vec![] }
+    }
+
+    pub fn set_identifiers(&mut self, identifiers: Vec<String>) {
+        self.identifiers = identifiers;
+    }
+
+    // Début du mot sous le curseur et complétions possibles
+    pub fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        // Le mot commence après le dernier séparateur, qui peut occuper plusieurs octets
+        let start = line[..pos].char_indices().rev()
+            .find(|(_, c)| !(c.is_ascii_alphanumeric() || *c == '_'))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let word = &line[start..pos];
+        if line[..start].trim() == ":" {
+            let commands = COMMANDS.iter()
+                .filter(|c| c[1..].starts_with(word))
+                .map(|c| c[1..].to_string())
+                .collect();
+            return (start, commands);
+        }
+        if word.is_empty() {
+            return (pos, vec![]);
+        }
+        let mut words: Vec<String> = self.keywords.iter()
+            .chain(self.identifiers.iter())
+            .filter(|w| w.starts_with(word))
+            .cloned()
+            .collect();
+        words.sort();
+        words.dedup();
+        (start, words)
+    }
+}
+
+impl Completer for MicroRustHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Hinter for MicroRustHelper {
+    type Hint = String;
+}
+
+impl Highlighter for MicroRustHelper {}
+
+impl Validator for MicroRustHelper {}
+
+impl Helper for MicroRustHelper {}
+
+pub type MicroRustEditor = Editor<MicroRustHelper, DefaultHistory>;
+
+pub fn new_editor() -> rustyline::Result<MicroRustEditor> {
+    let mut editor = Editor::new()?;
+    editor.set_helper(Some(MicroRustHelper::new()));
+    Ok(editor)
+}
+
+// Historique conservé d'une session à l'autre dans `~/.microrust_history`
+pub fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".microrust_history"))
+}
@@ -1,14 +1,18 @@
 use std::fmt::{self, Display};
 use crate::parsing::expression::Expression;
+use crate::parsing::span::Span;
 use crate::identifier::Identifier;
 use crate::memory::Address;
 use crate::parser::ParseError;
+use crate::compiler::CompileError;
 use crate::r#type::Type;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Error {
     ParseError(ParseError),
     EvalError(EvalError),
+    // Programme que la machine à pile ne sait pas encore exécuter
+    CompileError(CompileError),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -26,6 +30,26 @@ pub enum EvalError {
     MovedValue(Option<Expression>),
     CannotMoveOwnedValue(Option<Expression>),
     CannotFreeOwnedValue(Option<Expression>),
+    UnknownField(Type, Identifier),
+    MissingField(Identifier, Identifier),
+    UnknownMethod(Type, Identifier),
+    NotCallable(Expression),
+    NotAddressable(Expression),
+    WrongArgumentCount{function: Identifier, expected: usize, found: usize},
+    DanglingReference(Identifier),
+    IfBranchMismatch{expected: Type, found: Type},
+    // Type de la branche d'un `if` sans `else`, qui devrait valoir ()
+    MissingElse(Type),
+    // `break` et `continue` remontent jusqu'à la boucle englobante
+    Break,
+    Continue,
+    OutsideLoop(&'static str),
+    NativeError{function: Identifier, message: String},
+    InvalidFormat(String),
+    // `panic!` ou assertion échouée, rattachée à l'appel de la macro
+    Panic{message: String, span: Option<Span>},
+    // Erreur accompagnée de la portion du texte source qui l'a provoquée
+    At(Span, Box<EvalError>),
 }
 
 impl From<ParseError> for Error {
@@ -40,6 +64,12 @@ impl From<EvalError> for Error {
     }
 }
 
+impl From<CompileError> for Error {
+    fn from(e: CompileError) -> Self {
+        Error::CompileError(e)
+    }
+}
+
 impl Display for EvalError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use EvalError::*;
@@ -49,15 +79,35 @@ impl Display for EvalError {
             AlreadyDefined(id) => write!(f, "Identifier `{}` already defined.", id),
             NotMutable(e) => write!(f, "Value {}is not mutable.", e.as_ref().map(|e| format!("at `{}` ", e)).unwrap_or("".to_string())),
             TypeMismatch { expression, expected, found} => {
-                write!(f, "Type mismatch in expression `{}`. Expected: {}. {}", expression, expected, found.map(|f| format!("Found: {}", f)).unwrap_or("".to_string()))
+                write!(f, "Type mismatch in expression `{}`. Expected: {}. {}", expression, expected, found.as_ref().map(|f| format!("Found: {}", f)).unwrap_or("".to_string()))
             },
             NonAllocatedCell(e) => write!(f, "Cell {}is not allocated.", e.as_ref().map(|e| format!("at `{}` ", e)).unwrap_or("".to_string())),
-            NonInitializedValue(e) => write!(f, "Value {} is not initialized.", e.as_ref().map(|e| format!("in `{}` ", e)).unwrap_or("".to_string())),
+            NonInitializedValue(e) => write!(f, "Value {}is not initialized.", e.as_ref().map(|e| format!("in `{}` ", e)).unwrap_or("".to_string())),
             UseAfterFree(e) => write!(f, "{}use after free.", e.as_ref().map(|e| format!("`{}` is a ", e)).unwrap_or("".to_string())),
             MemoryLeak(a) => write!(f, "leaking {}", a),
             MovedValue(e) => write!(f, "{} has been moved", e.as_ref().map(|e| format!("`{}`", e)).unwrap_or("value".to_string())),
             CannotMoveOwnedValue(e) => write!(f, "cannot move {}, owned value with move semantics", e.as_ref().map(|e| format!("`{}`", e)).unwrap_or("this value".to_string())),
             CannotFreeOwnedValue(e) => write!(f, "cannot free {}, owned value", e.as_ref().map(|e| format!("`{}`", e)).unwrap_or("this value".to_string())),
+            UnknownField(t, id) => write!(f, "No field `{}` on type `{}`.", id, t),
+            MissingField(s, id) => write!(f, "Missing field `{}` in initializer of `{}`.", id, s),
+            UnknownMethod(t, id) => write!(f, "No method named `{}` found for type `{}`.", id, t),
+            NotCallable(e) => write!(f, "`{}` is not a function.", e),
+            NotAddressable(e) => write!(f, "Cannot take the address of `{}`.", e),
+            DanglingReference(id) => write!(f, "Dangling reference to `{}`, the block where it was declared has ended.", id),
+            IfBranchMismatch { expected, found } => {
+                write!(f, "`if` and `else` have incompatible types. Expected: {}. Found: {}", expected, found)
+            },
+            MissingElse(t) => write!(f, "`if` may be missing an `else` clause. Expected: unit. Found: {}", t),
+            Break => write!(f, "`break` outside of a loop."),
+            Continue => write!(f, "`continue` outside of a loop."),
+            OutsideLoop(kw) => write!(f, "`{}` outside of a loop.", kw),
+            WrongArgumentCount { function, expected, found } => {
+                write!(f, "Function `{}` takes {} argument(s) but {} were supplied.", function, expected, found)
+            },
+            NativeError { function, message } => write!(f, "Function `{}` failed: {}", function, message),
+            InvalidFormat(message) => write!(f, "Invalid format: {}.", message),
+            Panic { message, .. } => write!(f, "Panicked: {}", message),
+            At(_, e) => write!(f, "{}", e),
         }
     }
 }
@@ -68,11 +118,100 @@ impl Display for Error {
         match self {
             ParseError(e) => write!(f, "Parse Error: {}", e),
             EvalError(e) => write!(f, "Evaluation Error: {}", e),
+            CompileError(e) => write!(f, "Compile Error: {}", e),
+        }
+    }
+}
+
+impl Error {
+    // Message de l'erreur, suivi de la portion du source qui l'a provoquée
+    pub fn report(&self) -> String {
+        match self {
+            Error::EvalError(e) => match e.span() {
+                Some(span) => format!("{}\n{}", self, span.snippet()),
+                None => self.to_string(),
+            },
+            Error::ParseError(_) | Error::CompileError(_) => self.to_string(),
+        }
+    }
+
+    // Nom de la variante de l'erreur, pour les sorties destinées aux programmes
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::ParseError(e) => e.kind(),
+            Error::EvalError(e) => e.kind(),
+            Error::CompileError(e) => e.kind(),
         }
     }
 }
 
 impl EvalError {
+    // Rattache l'erreur au nœud en cours d'évaluation, sauf si elle l'est déjà à un nœud plus précis.
+    // L'expression fautive portée par l'erreur, si elle a une portion, est préférée au nœud.
+    pub fn at(self, span: &Span) -> Self {
+        use EvalError::*;
+        match self {
+            Panic { message, span: None } => Panic { message, span: Some(span.clone()) },
+            At(_, _) | Break | Continue | Panic { .. } => self,
+            e => {
+                let span = e.expression().and_then(Expression::span).unwrap_or(span).clone();
+                At(span, Box::new(e))
+            }
+        }
+    }
+
+    fn expression(&self) -> Option<&Expression> {
+        use EvalError::*;
+        match self {
+            DivisionByZero(e) | NotCallable(e) | NotAddressable(e) | TypeMismatch { expression: e, .. } => Some(e),
+            NotMutable(e) | NonAllocatedCell(e) | NonInitializedValue(e) | UseAfterFree(e)
+            | MovedValue(e) | CannotMoveOwnedValue(e) | CannotFreeOwnedValue(e) => e.as_ref(),
+            _ => None,
+        }
+    }
+
+    // Nom de la variante, pour les sorties destinées aux programmes
+    pub fn kind(&self) -> &'static str {
+        use EvalError::*;
+        match self {
+            DivisionByZero(_) => "DivisionByZero",
+            Undefined(_) => "Undefined",
+            AlreadyDefined(_) => "AlreadyDefined",
+            NotMutable(_) => "NotMutable",
+            TypeMismatch { .. } => "TypeMismatch",
+            NonAllocatedCell(_) => "NonAllocatedCell",
+            NonInitializedValue(_) => "NonInitializedValue",
+            UseAfterFree(_) => "UseAfterFree",
+            MemoryLeak(_) => "MemoryLeak",
+            MovedValue(_) => "MovedValue",
+            CannotMoveOwnedValue(_) => "CannotMoveOwnedValue",
+            CannotFreeOwnedValue(_) => "CannotFreeOwnedValue",
+            UnknownField(_, _) => "UnknownField",
+            MissingField(_, _) => "MissingField",
+            UnknownMethod(_, _) => "UnknownMethod",
+            NotCallable(_) => "NotCallable",
+            NotAddressable(_) => "NotAddressable",
+            WrongArgumentCount { .. } => "WrongArgumentCount",
+            DanglingReference(_) => "DanglingReference",
+            IfBranchMismatch { .. } => "IfBranchMismatch",
+            MissingElse(_) => "MissingElse",
+            Break => "Break",
+            Continue => "Continue",
+            OutsideLoop(_) => "OutsideLoop",
+            NativeError { .. } => "NativeError",
+            InvalidFormat(_) => "InvalidFormat",
+            Panic { .. } => "Panic",
+            At(_, e) => e.kind(),
+        }
+    }
+
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            EvalError::At(span, _) | EvalError::Panic { span: Some(span), .. } => Some(span),
+            _ => None,
+        }
+    }
+
     pub fn with_expression_info(&self, e: Expression) -> Self {
         use EvalError::*;
         match self {
@@ -80,6 +219,7 @@ impl EvalError {
             NonAllocatedCell(None) => NonAllocatedCell(Some(e)),
             NonInitializedValue(None) => NonInitializedValue(Some(e)),
             UseAfterFree(None) => UseAfterFree(Some(e)),
+            CannotFreeOwnedValue(None) => CannotFreeOwnedValue(Some(e)),
             _ => self.clone(),
         }
     }
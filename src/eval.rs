@@ -1,10 +1,17 @@
-use crate::Identifier;
+use crate::identifier::Identifier;
 use crate::parsing::binop::Binop::*;
 use crate::error::EvalError;
 use crate::error::EvalError::{DivisionByZero};
 use crate::memory::Address;
 use crate::parsing::expression::Expression;
 use crate::parsing::expression::Expression::*;
+use crate::parsing::function::{self, Capture, Function, NativeFunction, Receiver};
+use crate::parsing::format::{self, ArgRef, Piece};
+use crate::parsing::macro_call::{self, MacroArg};
+use crate::output::{self, Stream};
+use crate::debugger;
+use crate::trace;
+use std::rc::Rc;
 use crate::parsing::instruction::Instruction;
 use crate::namespace::NameSpace;
 use crate::namespacestack::NameSpaceStack;
@@ -13,6 +20,11 @@ use crate::r#type::Type;
 use crate::parsing::value::Value;
 use crate::parsing::value::Value::Unit;
 
+// Indentation du journal de `--trace` : le NameSpace global n'est pas compté
+fn trace_depth(nss: &NameSpaceStack) -> usize {
+    nss.namespaces().len().saturating_sub(1)
+}
+
 impl Expression {
     fn eval_and_cast_to_int(&self, nss: &mut NameSpaceStack) -> Result<isize, EvalError> {
         let v = self.eval(nss)?;
@@ -25,7 +37,7 @@ impl Expression {
 
     pub fn eval(&self, nss : &mut NameSpaceStack) -> Result<Value, EvalError> {
         match self {
-            Const(v) => Ok(Value::from(*v)),
+            Const(v) => Ok(Value::from(v.clone())),
             BinOp(e1, op, e2) => {
                 match op {
                     Add => {
@@ -86,6 +98,7 @@ impl Expression {
                         let v2 = e2.eval(nss)?;
                         match (v1, v2) {
                             (Value::Integer(i1), Value::Integer(i2)) => Ok(Value::Boolean(i1 == i2)),
+                            (Value::Str(s1), Value::Str(s2)) => Ok(Value::Boolean(s1 == s2)),
                             (Value::Boolean(b1), Value::Boolean(b2)) => Ok(Value::Boolean(b1 == b2)),
                             (Value::Boolean(_b), Value::Integer(_i)) => Err(EvalError::TypeMismatch{
                                 expression: *e2.clone(),
@@ -105,6 +118,7 @@ impl Expression {
                         let v2 = e2.eval(nss)?;
                         match (v1, v2) {
                             (Value::Integer(i1), Value::Integer(i2)) => Ok(Value::Boolean(i1 != i2)),
+                            (Value::Str(s1), Value::Str(s2)) => Ok(Value::Boolean(s1 != s2)),
                             (Value::Boolean(b1), Value::Boolean(b2)) => Ok(Value::Boolean(b1 != b2)),
                             (Value::Boolean(_b), Value::Integer(_i)) => Err(EvalError::TypeMismatch{
                                 expression: *e2.clone(),
@@ -120,17 +134,28 @@ impl Expression {
                         }
                     }
                     And => {
+                        // Le second opérande n'est évalué que si le premier est vrai
                         let v1 = e1.eval(nss)?;
+                        if let Ok(false) = v1.to_bool() {
+                            return Ok(Value::Boolean(false))
+                        }
                         let v2 = e2.eval(nss)?;
-                        let b1 = v1.to_bool();
-                        let b2 = v2.to_bool();
-                        match (b1, b2) {
+                        match (v1.to_bool(), v2.to_bool()) {
                             (Ok(true), Ok(true)) => Ok(Value::Boolean(true)),
-                            (Ok(false), _) => Ok(Value::Boolean(false)),
                             (_, Ok(false)) => Ok(Value::Boolean(false)),
                             _ => Err(EvalError::Undefined(Identifier::from("And")))
                         }
                     }
+                    Range => {
+                        let v1 = e1.eval_and_cast_to_int(nss)?;
+                        let v2 = e2.eval_and_cast_to_int(nss)?;
+                        Ok(Value::Range(v1, v2))
+                    }
+                    RangeInclusive => {
+                        let v1 = e1.eval_and_cast_to_int(nss)?;
+                        let v2 = e2.eval_and_cast_to_int(nss)?;
+                        Ok(Value::Range(v1, v2 + 1))
+                    }
                     Or => {
                         let v1 = e1.eval(nss)?;
                         let b1 = v1.to_bool();
@@ -148,7 +173,7 @@ impl Expression {
                 }
             },
             Identifier(id) => {
-                nss.find(id.into())
+                nss.find(id)
             }
             Conditional {cond, cond_true, cond_false} => {
                 let v = cond.eval(nss)?;
@@ -163,22 +188,442 @@ impl Expression {
                 eval_lexpr(lexpr, nss)
             }
             NewPtr => {
-                todo!()
+                Ok(Value::Pointer(nss.malloc()))
+            }
+            Deref(e) => {
+                let addr = e.eval_to_pointer(nss)?;
+                nss.read(&addr).map_err(|err| err.with_expression_info(self.clone()))
             }
-            Deref(_) => todo!(),
             AmpersAnd(_) => {
                 let addr = self.eval_to_address(nss)?;
                 Ok(Value::Pointer(addr))
             }
+            StructLit(name, fields) => {
+                let s = nss.get_struct(name)?;
+                let mut values = vec![];
+                for (id, e) in fields {
+                    let expected = s.field_type(id)
+                        .ok_or(EvalError::UnknownField(Type::Struct(name.clone()), id.clone()))?;
+                    let v = e.eval(nss)?;
+                    if Type::from(&v) != *expected {
+                        return Err(EvalError::TypeMismatch{
+                            expression: e.clone(),
+                            expected: expected.clone(),
+                            found: Some(Type::from(&v))})
+                    }
+                    values.push((id.clone(), v));
+                }
+                // Les champs sont rangés dans l'ordre de la déclaration
+                let values = s.fields.iter().map(|(id, _)| {
+                    values.iter()
+                        .find(|(f, _)| f == id)
+                        .cloned()
+                        .ok_or(EvalError::MissingField(name.clone(), id.clone()))
+                }).collect::<Result<Vec<_>, EvalError>>()?;
+                Ok(Value::Struct(name.clone(), values))
+            }
+            Field(e, id) => {
+                let (_addr, v) = deref_all(e.eval(nss)?, nss)?;
+                v.get_field(id).cloned()
+            }
+            Path(ty, id) => {
+                nss.get_method(ty, id)
+                    .map(Value::Function)
+                    .ok_or(EvalError::UnknownMethod(Type::Struct(ty.clone()), id.clone()))
+            }
+            Call(callee, args) => {
+                match callee.eval(nss)? {
+                    Value::Function(fun) => call_function(&fun, None, &[], args, self, nss),
+                    Value::Closure(closure) => call_function(&closure.fun, None, &closure.captures, args, self, nss),
+                    Value::Native(native) => call_native(&native, args, self, nss),
+                    _ => Err(EvalError::NotCallable(*callee.clone())),
+                }
+            }
+            If { cond, cond_true, cond_false } => {
+                // Le type des branches a été vérifié à la déclaration, par `typing::check_branches`
+                let v = cond.eval(nss)?;
+                match v.to_bool() {
+                    Ok(true) => {
+                        let (_id, v) = cond_true.exec(nss)?;
+                        // Sans `else`, un `if` vaut toujours ()
+                        Ok(if cond_false.is_some() { v } else { Unit })
+                    }
+                    Ok(false) => match cond_false {
+                        Some(cond_false) => Ok(cond_false.exec(nss)?.1),
+                        None => Ok(Unit),
+                    },
+                    Err(t) => Err(EvalError::TypeMismatch{
+                        expression: *cond.clone(),
+                        expected: Type::Bool,
+                        found: Some(t)}),
+                }
+            }
+            Closure { is_move, fun } => {
+                // Les variables libres du corps sont résolues au moment de la création
+                let mut ids = vec![];
+                fun.body.identifiers(&mut ids);
+                let mut captures: Vec<(Identifier, Capture)> = vec![];
+                for id in ids {
+                    if fun.params.iter().any(|p| p.id == id) || captures.iter().any(|(c, _)| *c == id) {
+                        continue;
+                    }
+                    if let Ok(addr) = nss.get_address(&id) {
+                        let capture = if *is_move {
+                            Capture::ByValue(nss.read(&addr)?)
+                        } else {
+                            let ns_id = nss.namespace_id(&addr).unwrap();
+                            Capture::ByRef(addr, ns_id)
+                        };
+                        captures.push((id, capture));
+                    }
+                }
+                Ok(Value::Closure(Rc::new(function::Closure { fun: fun.clone(), captures })))
+            }
+            MethodCall(recv, name, args) => {
+                // Un receveur qui désigne une cellule n'est évalué qu'une fois, par sa place ;
+                // pour `&mut self`, c'est la cellule visée qui doit être mutable
+                let place = match recv.to_lexpr() {
+                    Some(lexpr) => Some(lexpr_place(&lexpr, false, nss)
+                        .and_then(|(addr, path)| read_place(&addr, &path, nss).map(|v| (addr, path, v)))
+                        .map_err(|err| {
+                            let err = err.with_expression_info(ValueAt(lexpr.clone()));
+                            match recv.span() {
+                                Some(span) => err.at(span),
+                                None => err,
+                            }
+                        })?),
+                    None => None,
+                };
+                let (place, v) = match place {
+                    Some((addr, path, v)) => (Some((addr, path)), v),
+                    None => (None, recv.eval(nss)?),
+                };
+                let (addr, target) = deref_all(v, nss)?;
+                let ty = Type::from(&target);
+                let fun = match &ty {
+                    Type::Struct(s) => nss.get_method(s, name).filter(|fun| fun.receiver.is_some()),
+                    _ => None,
+                }.ok_or(EvalError::UnknownMethod(ty, name.clone()))?;
+                let kind = match fun.receiver {
+                    Some(kind @ (Receiver::Ref | Receiver::RefMut)) => kind,
+                    _ => return call_function(&fun, Some(target), &[], args, self, nss),
+                };
+                // `&self` reçoit l'adresse du receveur : celle qu'un pointeur désigne déjà, sinon sa place
+                let (cell, path) = match (addr, place) {
+                    (Some(addr), _) => (addr, vec![]),
+                    (None, Some(place)) => place,
+                    // Un receveur temporaire, comme `Point { x: 3, y: 4 }`, est d'abord rangé dans une cellule
+                    (None, None) => return with_temporary(target, nss, |tmp, nss| {
+                        call_function(&fun, Some(Value::Pointer(tmp)), &[], args, self, nss)
+                    }),
+                };
+                if kind == Receiver::RefMut && !nss.is_mutable(&cell)? {
+                    return Err(EvalError::NotMutable(Some(*recv.clone())))
+                }
+                if path.is_empty() {
+                    return call_function(&fun, Some(Value::Pointer(cell)), &[], args, self, nss)
+                }
+                // Une adresse ne désigne pas un champ : la méthode travaille sur une copie, recopiée ensuite
+                let (res, v) = with_temporary(target, nss, |tmp, nss| {
+                    let res = call_function(&fun, Some(Value::Pointer(tmp.clone())), &[], args, self, nss)?;
+                    Ok((res, nss.read(&tmp)?))
+                })?;
+                if kind == Receiver::RefMut {
+                    nss.write(&cell, &path, v)?;
+                }
+                Ok(res)
+            }
+            Macro(name, args) => eval_macro(name, args, nss),
+            Spanned(span, e) => {
+                let res = e.eval(nss).map_err(|err| err.at(span));
+                if trace::enabled() {
+                    trace::node(self, res.as_ref(), trace_depth(nss));
+                }
+                res
+            }
+        }
+    }
+
+    // Expression gauche équivalente, pour une expression qui désigne une cellule plutôt qu'une valeur temporaire
+    fn to_lexpr(&self) -> Option<LeftExpression> {
+        match self {
+            Identifier(id) => Some(LeftExpression::Identifier(id.clone())),
+            ValueAt(lexpr) => Some(lexpr.clone()),
+            Field(e, field) => e.to_lexpr().map(|lexpr| LeftExpression::Field(Box::new(lexpr), field.clone())),
+            Deref(e) => e.to_lexpr().map(|lexpr| LeftExpression::Star(Box::new(lexpr))),
+            Spanned(_, e) => e.to_lexpr(),
+            _ => None,
         }
     }
 
     fn eval_to_address(&self, nss: &mut NameSpaceStack) -> Result<Address, EvalError> {
         match self {
             AmpersAnd(e) => e.eval_to_address(nss),
-            Identifier(id) => nss.get_address(id.into()),
-            _ => todo!(),
+            Identifier(id) => nss.get_address(id),
+            ValueAt(LeftExpression::Identifier(id)) => nss.get_address(id),
+            Deref(e) => e.eval_to_pointer(nss),
+            Spanned(span, e) => e.eval_to_address(nss).map_err(|err| err.at(span)),
+            _ => Err(EvalError::NotAddressable(self.clone())),
+        }
+    }
+
+    fn eval_to_pointer(&self, nss: &mut NameSpaceStack) -> Result<Address, EvalError> {
+        let v = self.eval(nss)?;
+        v.to_address()
+            .map_err(|t| EvalError::TypeMismatch{
+                expression: self.clone(),
+                expected: Type::Address,
+                found: Some(t)})
+    }
+}
+
+// Range une valeur temporaire dans une cellule mutable, le temps de l'appel `f`
+fn with_temporary<T>(v: Value, nss: &mut NameSpaceStack, f: impl FnOnce(Address, &mut NameSpaceStack) -> Result<T, EvalError>) -> Result<T, EvalError> {
+    // Ce nom ne peut pas être écrit dans un programme
+    let id = Identifier::from("<temporary>");
+    let mut ns = NameSpace::new();
+    ns.declare(&id, true, v)?;
+    nss.push(ns);
+    let res = nss.get_address(&id).and_then(|addr| f(addr, nss));
+    nss.pop();
+    res
+}
+
+// Suit les pointeurs jusqu'à une valeur, en renvoyant la dernière adresse traversée
+fn deref_all(v: Value, nss: &NameSpaceStack) -> Result<(Option<Address>, Value), EvalError> {
+    let mut addr = None;
+    let mut v = v;
+    while let Value::Pointer(a) = v {
+        v = nss.read(&a)?;
+        addr = Some(a);
+    }
+    Ok((addr, v))
+}
+
+fn call_function(fun: &Function, receiver: Option<Value>, captures: &[(Identifier, Capture)], args: &[Expression], call: &Expression, nss: &mut NameSpaceStack) -> Result<Value, EvalError> {
+    // Appelée par son chemin (`Point::norm(&p)`), une méthode prend son receveur en premier argument
+    let expected = fun.params.len() + usize::from(fun.receiver.is_some() && receiver.is_none());
+    if args.len() != expected {
+        return Err(EvalError::WrongArgumentCount{function: fun.name.clone(), expected, found: args.len()})
+    }
+    let mut args = args.iter();
+    let mut ns = NameSpace::new();
+    if let Some(kind) = fun.receiver {
+        let v = match receiver {
+            Some(v) => v,
+            None => {
+                let e = args.next().unwrap();
+                let v = e.eval(nss)?;
+                if kind != Receiver::Value && v.to_address().is_err() {
+                    return Err(EvalError::TypeMismatch{
+                        expression: e.clone(),
+                        expected: Type::Address,
+                        found: Some(Type::from(&v))})
+                }
+                v
+            }
+        };
+        ns.declare(&Identifier::from("self"), kind == Receiver::RefMut, v)?;
+    }
+    let mut refs = vec![];
+    for (id, capture) in captures {
+        match capture {
+            Capture::ByValue(v) => ns.declare(id, false, v.clone())?,
+            Capture::ByRef(addr, ns_id) => refs.push((id.clone(), addr.clone(), *ns_id)),
+        }
+    }
+    for (param, e) in fun.params.iter().zip(args) {
+        let v = e.eval(nss)?;
+        if !param.ty.accepts(&Type::from(&v)) {
+            return Err(EvalError::TypeMismatch{
+                expression: e.clone(),
+                expected: param.ty.clone(),
+                found: Some(Type::from(&v))})
         }
+        ns.declare(&param.id, param.mutable, v)?;
+    }
+    nss.push_frame(fun.name.clone(), ns, refs);
+    let res = fun.body.exec(nss);
+    nss.pop_frame();
+    // Une boucle de l'appelant ne doit pas intercepter un `break` de la fonction
+    let (_id, v) = match res {
+        Err(EvalError::Break) => Err(EvalError::OutsideLoop("break")),
+        Err(EvalError::Continue) => Err(EvalError::OutsideLoop("continue")),
+        res => res,
+    }?;
+    // Le résultat d'une fonction sans type de retour est ignoré
+    if fun.ret == Type::Unit {
+        return Ok(Unit)
+    }
+    if !fun.ret.accepts(&Type::from(&v)) {
+        return Err(EvalError::TypeMismatch{
+            expression: call.clone(),
+            expected: fun.ret.clone(),
+            found: Some(Type::from(&v))})
+    }
+    Ok(v)
+}
+
+// Les arguments d'une fonction de l'hôte sont vérifiés avant l'appel, son résultat après
+fn call_native(native: &NativeFunction, args: &[Expression], call: &Expression, nss: &mut NameSpaceStack) -> Result<Value, EvalError> {
+    if args.len() != native.params.len() {
+        return Err(EvalError::WrongArgumentCount{function: native.name.clone(), expected: native.params.len(), found: args.len()})
+    }
+    let mut values = vec![];
+    for (ty, e) in native.params.iter().zip(args) {
+        let v = e.eval(nss)?;
+        if !ty.accepts(&Type::from(&v)) {
+            return Err(EvalError::TypeMismatch{
+                expression: e.clone(),
+                expected: ty.clone(),
+                found: Some(Type::from(&v))})
+        }
+        values.push(v);
+    }
+    let v = (native.fun)(&values)
+        .map_err(|message| EvalError::NativeError{function: native.name.clone(), message})?;
+    if !native.ret.accepts(&Type::from(&v)) {
+        return Err(EvalError::TypeMismatch{
+            expression: call.clone(),
+            expected: native.ret.clone(),
+            found: Some(Type::from(&v))})
+    }
+    Ok(v)
+}
+
+pub(crate) fn panic(message: String) -> EvalError {
+    EvalError::Panic { message, span: None }
+}
+
+fn eval_macro(name: &macro_call::Macro, args: &[MacroArg], nss: &mut NameSpaceStack) -> Result<Value, EvalError> {
+    use macro_call::Macro::*;
+    // Les assertions prennent leurs opérandes avant un message facultatif
+    let operands = match name {
+        Assert => 1,
+        AssertEq | AssertNe => 2,
+        _ => 0,
+    };
+    if args.len() < operands {
+        return Err(EvalError::WrongArgumentCount{function: Identifier::from(name.to_string().as_str()), expected: operands, found: args.len()})
+    }
+    let (operands, rest) = args.split_at(operands);
+    match name {
+        Format => Ok(Value::Str(Rc::from(format_args(name, args, nss)?))),
+        Print => {
+            output::write(Stream::Stdout, &format_args(name, args, nss)?);
+            Ok(Unit)
+        },
+        Println => {
+            output::write(Stream::Stdout, &format!("{}\n", format_args(name, args, nss)?));
+            Ok(Unit)
+        },
+        Eprintln => {
+            output::write(Stream::Stderr, &format!("{}\n", format_args(name, args, nss)?));
+            Ok(Unit)
+        },
+        Panic if args.is_empty() => Err(panic("explicit panic".to_string())),
+        Panic => Err(panic(format_args(name, args, nss)?)),
+        Assert => {
+            let cond = &operands[0].expr;
+            let v = cond.eval(nss)?;
+            match v.to_bool() {
+                Ok(true) => Ok(Unit),
+                Ok(false) if rest.is_empty() => Err(panic(format!("assertion failed: {}", cond))),
+                Ok(false) => Err(panic(format_args(name, rest, nss)?)),
+                Err(t) => Err(EvalError::TypeMismatch{expression: cond.clone(), expected: Type::Bool, found: Some(t)}),
+            }
+        },
+        AssertEq | AssertNe => {
+            let left = operands[0].expr.eval(nss)?;
+            let right = operands[1].expr.eval(nss)?;
+            if assertion_holds(name, &left, &right, &operands[1].expr)? {
+                return Ok(Unit)
+            }
+            let message = if rest.is_empty() { None } else { Some(format_args(name, rest, nss)?) };
+            Err(assertion_failed(name, &left, &right, message))
+        },
+    }
+}
+
+// Comparaison de `assert_eq!` ou `assert_ne!`, entre deux valeurs du même type
+pub(crate) fn assertion_holds(name: &macro_call::Macro, left: &Value, right: &Value, right_expr: &Expression) -> Result<bool, EvalError> {
+    if Type::from(left) != Type::from(right) {
+        return Err(EvalError::TypeMismatch{
+            expression: right_expr.clone(),
+            expected: Type::from(left),
+            found: Some(Type::from(right))})
+    }
+    Ok((left == right) == (*name == macro_call::Macro::AssertEq))
+}
+
+pub(crate) fn assertion_failed(name: &macro_call::Macro, left: &Value, right: &Value, message: Option<String>) -> EvalError {
+    let op = if *name == macro_call::Macro::AssertEq { "==" } else { "!=" };
+    let message = message.map(|m| format!(": {}", m)).unwrap_or_default();
+    panic(format!("assertion `left {} right` failed{}\n  left: {}\n right: {}", op, message, left.debug(), right.debug()))
+}
+
+// Texte d'une macro de formatage : la chaîne de format, dont les `{}` sont remplacés par les arguments
+fn format_args(name: &macro_call::Macro, args: &[MacroArg], nss: &mut NameSpaceStack) -> Result<String, EvalError> {
+    if format_literal(name, args)?.is_none() {
+        return Ok(String::new());
+    }
+    let values = args[1..].iter().map(|arg| arg.expr.eval(nss)).collect::<Result<Vec<_>, _>>()?;
+    format_values(name, args, &values, &mut |id| nss.find(id))
+}
+
+// Chaîne de format d'une macro, vérifiée avant l'évaluation des arguments ; `println!()` n'en a pas
+pub(crate) fn format_literal<'a>(name: &macro_call::Macro, args: &'a [MacroArg]) -> Result<Option<&'a str>, EvalError> {
+    match args.first() {
+        None => Ok(None),
+        Some(first) => match first.expr.as_str() {
+            Some(format) if first.name.is_none() => Ok(Some(format)),
+            _ => Err(EvalError::InvalidFormat(format!("{} expects a string literal as its first argument", name))),
+        },
+    }
+}
+
+// Remplace les `{}` de la chaîne de format, premier élément de `args`, par les valeurs des arguments qui la suivent.
+// Un nom absent des arguments désigne une variable, obtenue par `lookup`.
+pub(crate) fn format_values(name: &macro_call::Macro, args: &[MacroArg], values: &[Value], lookup: &mut dyn FnMut(&Identifier) -> Result<Value, EvalError>) -> Result<String, EvalError> {
+    let Some(format) = format_literal(name, args)? else {
+        return Ok(String::new());
+    };
+    let args = &args[1..];
+    let pieces = format::parse(format).map_err(EvalError::InvalidFormat)?;
+    let mut used = vec![false; args.len()];
+    let mut next = 0;
+    let mut text = String::new();
+    for piece in pieces {
+        let placeholder = match piece {
+            Piece::Text(s) => {
+                text.push_str(&s);
+                continue;
+            },
+            Piece::Placeholder(placeholder) => placeholder,
+        };
+        let i = match &placeholder.arg {
+            ArgRef::Next => {
+                next += 1;
+                Some(next - 1)
+            },
+            ArgRef::Index(i) => Some(*i),
+            ArgRef::Name(id) => args.iter().position(|arg| arg.name.as_ref() == Some(id)),
+        };
+        let value = match (i, &placeholder.arg) {
+            (Some(i), _) if i < values.len() => {
+                used[i] = true;
+                values[i].clone()
+            },
+            (None, ArgRef::Name(id)) => lookup(id)?,
+            (Some(i), _) => return Err(EvalError::InvalidFormat(
+                format!("invalid reference to positional argument {} ({} argument(s) given)", i, values.len()))),
+            (None, _) => unreachable!(),
+        };
+        text.push_str(&format::render(&placeholder, &value));
+    }
+    match used.iter().position(|used| !used) {
+        Some(i) => Err(EvalError::InvalidFormat(format!("argument `{}` is never used", args[i].expr))),
+        None => Ok(text),
     }
 }
 
@@ -187,53 +632,107 @@ fn eval_lexpr(lexpr: &LeftExpression, nss: &mut NameSpaceStack) -> Result<Value,
         LeftExpression::Identifier(id) => {
             nss.find(id)
         }
-        _ => todo!()
+        LeftExpression::Star(_) | LeftExpression::Field(_, _) => {
+            let (addr, path) = eval_lexpr_to_place(lexpr, nss)?;
+            read_place(&addr, &path, nss).map_err(|err| err.with_expression_info(ValueAt(lexpr.clone())))
+        }
+    }
+}
+
+fn read_place(addr: &Address, path: &[Identifier], nss: &NameSpaceStack) -> Result<Value, EvalError> {
+    let mut v = nss.read(addr)?;
+    for field in path {
+        v = v.get_field(field)?.clone();
+    }
+    Ok(v)
+}
+
+// Cellule désignée par une expression gauche, et chemin des champs à suivre à l'intérieur.
+// On ne peut écrire à travers un pointeur que s'il est lui-même rangé dans une cellule mutable.
+fn eval_lexpr_to_place(lexpr: &LeftExpression, nss: &mut NameSpaceStack) -> Result<(Address, Vec<Identifier>), EvalError> {
+    lexpr_place(lexpr, true, nss)
+}
+
+// Sans `through_mutable`, les pointeurs traversés peuvent être rangés dans des cellules immuables
+fn lexpr_place(lexpr: &LeftExpression, through_mutable: bool, nss: &mut NameSpaceStack) -> Result<(Address, Vec<Identifier>), EvalError> {
+    match lexpr {
+        LeftExpression::Identifier(id) => Ok((nss.get_address(id)?, vec![])),
+        LeftExpression::Star(inner) => {
+            let (addr, path) = lexpr_place(inner, through_mutable, nss)?;
+            let v = read_place(&addr, &path, nss)?;
+            let target = v.to_address()
+                .map_err(|t| EvalError::TypeMismatch{
+                    expression: ValueAt(*inner.clone()),
+                    expected: Type::Address,
+                    found: Some(t)})?;
+            if through_mutable && !nss.is_mutable(&addr)? {
+                return Err(EvalError::NotMutable(Some(ValueAt(*inner.clone()))))
+            }
+            Ok((target, vec![]))
+        }
+        LeftExpression::Field(inner, field) => {
+            let (addr, mut path) = lexpr_place(inner, through_mutable, nss)?;
+            match read_place(&addr, &path, nss)? {
+                Value::Pointer(target) => {
+                    if through_mutable && !nss.is_mutable(&addr)? {
+                        return Err(EvalError::NotMutable(Some(ValueAt(*inner.clone()))))
+                    }
+                    Ok((target, vec![field.clone()]))
+                }
+                _ => {
+                    path.push(field.clone());
+                    Ok((addr, path))
+                }
+            }
+        }
     }
 }
 
 impl Instruction {
     pub fn exec(&self, nss: &mut NameSpaceStack) -> Result<(Option<Identifier>, Value), EvalError> {
+        // Le débogueur s'arrête avant chaque instruction du texte source
+        let span = match self {
+            Instruction::Spanned(span, _) => Some(span),
+            Instruction::Expr(e) => e.span(),
+            _ => None,
+        };
+        if let Some(span) = span {
+            debugger::before(self, span, nss)?;
+        }
         match self {
             Instruction::Expr(expr) => {
                 let v = expr.eval(nss);
                 Ok((None, v?))
             },
 
+            // Comme en Rust, une déclaration et une affectation valent ()
             Instruction::Let { id, mutable, expr } => {
                 let v = expr.eval(nss)?;
-                nss.declare(id, *mutable, v.clone())?;
-                Ok((Some(id.clone()), v))
+                nss.declare(id, *mutable, v)?;
+                Ok((Some(id.clone()), Unit))
             }
 
-            Instruction::Block(instrs) => {
+            Instruction::Block { instrs, tail } => {
                 let new_nss = NameSpace::new();
                 nss.push(new_nss);
                 let mut res = Unit;
                 for instr in instrs {
-                    let (_id, v) = instr.exec(nss).map_err(|err| {nss.pop(); err})?;
+                    let (_id, v) = instr.exec(nss).inspect_err(|_| {nss.pop();})?;
                     res = v;
                 }
                 nss.pop();
-                Ok((None, res))
+                Ok((None, if *tail { res } else { Unit }))
             }
             Instruction::WriteAt(lexpr, expr) => {
                 let v = expr.eval(nss)?;
                 match lexpr {
-                    LeftExpression::Identifier(id) => {
-                        nss.set(id, v.clone())?;
-                        Ok((None, v))
+                    LeftExpression::Identifier(id) => nss.set(id, v)?,
+                    _ => {
+                        let (addr, path) = eval_lexpr_to_place(lexpr, nss)?;
+                        nss.write(&addr, &path, v)?;
                     }
-                    _ => Err(EvalError::Undefined(Identifier::from("WriteAt")))
-                }
-            }
-            Instruction::IfElse{cond, cond_true, cond_false} => {
-                let v = cond.eval(nss)?;
-                let b = v.to_bool();
-                match b {
-                    Ok(true) => cond_true.exec(nss),
-                    Ok(false) => cond_false.exec(nss),
-                    _ => Err(EvalError::Undefined(Identifier::from("IfElse")))
                 }
+                Ok((None, Unit))
             }
             Instruction::While(cond, instr) => {
                 loop {
@@ -241,7 +740,11 @@ impl Instruction {
                     let b = v.to_bool();
                     match b {
                         Ok(true) => {
-                            let (_id, v) = instr.exec(nss).map_err(|err| {nss.pop(); err})?;
+                            match instr.exec(nss) {
+                                Err(EvalError::Break) => break,
+                                Err(EvalError::Continue) => continue,
+                                res => res?,
+                            };
                         }
                         Ok(false) => break,
                         _ => return Err(EvalError::Undefined(Identifier::from("While")))
@@ -249,8 +752,63 @@ impl Instruction {
                 }
                 Ok((None, Unit))
             }
-            Instruction::Free(_lexpr) => {
-                todo!()
+            Instruction::For(id, iter, instr) => {
+                let v = iter.eval(nss)?;
+                let (start, end) = match v {
+                    Value::Range(start, end) => (start, end),
+                    _ => return Err(EvalError::TypeMismatch{
+                        expression: iter.clone(),
+                        expected: Type::Range,
+                        found: Some(Type::from(&v))}),
+                };
+                for i in start..end {
+                    // Une nouvelle liaison immuable à chaque tour
+                    let mut ns = NameSpace::new();
+                    ns.declare(id, false, Value::Integer(i))?;
+                    nss.push(ns);
+                    let res = instr.exec(nss);
+                    nss.pop();
+                    match res {
+                        Err(EvalError::Break) => break,
+                        Err(EvalError::Continue) => continue,
+                        res => res?,
+                    };
+                }
+                Ok((None, Unit))
+            }
+            Instruction::Break => Err(EvalError::Break),
+            Instruction::Continue => Err(EvalError::Continue),
+            Instruction::Free(lexpr) => {
+                let v = eval_lexpr(lexpr, nss)?;
+                let addr = v.to_address()
+                    .map_err(|t| EvalError::TypeMismatch{
+                        expression: ValueAt(lexpr.clone()),
+                        expected: Type::Address,
+                        found: Some(t)})?;
+                nss.free(&addr).map_err(|e| e.with_expression_info(ValueAt(lexpr.clone())))?;
+                Ok((None, Unit))
+            }
+            Instruction::Fn(fun) => {
+                let v = Value::Function(fun.clone());
+                nss.declare(&fun.name, false, v.clone())?;
+                Ok((Some(fun.name.clone()), v))
+            }
+            Instruction::Struct(s) => {
+                nss.declare_struct(s.clone())?;
+                Ok((Some(s.name.clone()), Unit))
+            }
+            Instruction::Impl(name, funs) => {
+                for fun in funs {
+                    nss.declare_method(name, fun.clone())?;
+                }
+                Ok((None, Unit))
+            }
+            Instruction::Spanned(span, instr) => {
+                let res = instr.exec(nss).map_err(|err| err.at(span));
+                if trace::enabled() {
+                    trace::node(self, res.as_ref().map(|(_, v)| v), trace_depth(nss));
+                }
+                res
             }
         }
     }
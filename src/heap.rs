@@ -1,23 +1,119 @@
+use crate::error::EvalError;
+use crate::identifier::Identifier;
 use crate::memory::Address;
 use crate::memorycell::MemoryCell;
 use crate::parsing::value::Value;
 
-#[derive(Clone, Debug)]
-pub struct Heap(Vec<MemoryCell>);
+#[derive(Clone, Debug, Default)]
+pub struct Heap {
+    cells: Vec<MemoryCell>,
+    // Nombre d'allocations et de libérations
+    changes: u64,
+}
 
 impl Heap {
     pub fn new() -> Self {
-        Heap(Vec::new())
+        Heap { cells: Vec::new(), changes: 0 }
+    }
+
+    pub(crate) fn from_cells(cells: Vec<MemoryCell>) -> Self {
+        Heap { cells, changes: 0 }
     }
 
     pub fn malloc(&mut self, mutable: bool, value: Value) -> Address {
-        for addr in 0..self.0.len() {
-            if !self.0[addr].is_allocated() {
-                self.0[addr] = MemoryCell::new(mutable, value);
-                return Address::HeapAddress(addr);
-            }
+        self.insert(MemoryCell::new(mutable, value))
+    }
+
+    // `Ptr::new()` : une cellule mutable, sans valeur jusqu'à la première écriture
+    pub fn alloc(&mut self) -> Address {
+        self.insert(MemoryCell::uninitialized(true))
+    }
+
+    fn insert(&mut self, cell: MemoryCell) -> Address {
+        self.changes += 1;
+        match self.cells.iter().position(|cell| !cell.is_allocated()) {
+            Some(offset) => {
+                self.cells[offset] = cell;
+                Address::HeapAddress(offset)
+            },
+            None => {
+                self.cells.push(cell);
+                Address::HeapAddress(self.cells.len() - 1)
+            },
+        }
+    }
+
+    // Une cellule libérée ne peut plus être lue ni écrite
+    fn cell(&self, offset: usize) -> Result<&MemoryCell, EvalError> {
+        match self.cells.get(offset) {
+            Some(MemoryCell::NotAllocated) => Err(EvalError::UseAfterFree(None)),
+            Some(cell) => Ok(cell),
+            None => Err(EvalError::NonAllocatedCell(None)),
+        }
+    }
+
+    pub fn read(&self, offset: usize) -> Result<Value, EvalError> {
+        self.cell(offset)?.get_value().cloned()
+    }
+
+    pub fn is_mutable(&self, offset: usize) -> Result<bool, EvalError> {
+        Ok(self.cell(offset)?.is_mutable())
+    }
+
+    // Écrit la cellule, ou l'un des champs de la structure qu'elle contient
+    pub fn write(&mut self, offset: usize, path: &[Identifier], value: Value) -> Result<(), EvalError> {
+        let new_value = if path.is_empty() {
+            value
+        } else {
+            let mut new_value = self.read(offset)?;
+            new_value.set_field(path, value)?;
+            new_value
+        };
+        self.cell(offset)?;
+        self.cells[offset].set_value(new_value)
+    }
+
+    // Une cellule libérée garde sa place, elle peut être réutilisée par `malloc`
+    pub fn free(&mut self, addr: &Address) -> Result<(), EvalError> {
+        match addr {
+            Address::HeapAddress(offset) if self.cells.get(*offset).is_some_and(|cell| cell.is_allocated()) => {
+                self.cells[*offset] = MemoryCell::NotAllocated;
+                self.changes += 1;
+                Ok(())
+            },
+            Address::HeapAddress(offset) if *offset < self.cells.len() => Err(EvalError::UseAfterFree(None)),
+            Address::HeapAddress(_) => Err(EvalError::NonAllocatedCell(None)),
+            Address::StackAddress(_, _) => Err(EvalError::CannotFreeOwnedValue(None)),
+        }
+    }
+
+    pub fn cells(&self) -> &[MemoryCell] {
+        &self.cells
+    }
+
+    // Cellules encore allouées
+    pub fn allocated(&self) -> Vec<Address> {
+        (0..self.cells.len())
+            .filter(|addr| self.cells[*addr].is_allocated())
+            .map(Address::HeapAddress)
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn changes(&self) -> u64 {
+        self.changes
+    }
+}
+
+// Une ligne par cellule : `@[0] : isize = 1`
+impl std::fmt::Display for Heap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (addr, cell) in self.cells.iter().enumerate() {
+            writeln!(f, "{} : {}", Address::HeapAddress(addr), cell)?;
         }
-        self.0.push(MemoryCell::new(mutable, value));
-        Address::HeapAddress(self.0.len() - 1)
+        Ok(())
     }
-}
\ No newline at end of file
+}
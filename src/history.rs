@@ -0,0 +1,61 @@
+use std::collections::VecDeque;
+
+// Historique borné des états d'une session, pour `:undo` et `:redo`.
+// Les états les plus anciens sont oubliés au-delà de `limit`.
+#[derive(Debug, Clone)]
+pub struct History<T> {
+    past: VecDeque<T>,
+    future: Vec<T>,
+    limit: usize,
+}
+
+impl<T> History<T> {
+    pub fn new(limit: usize) -> Self {
+        History { past: VecDeque::new(), future: vec![], limit }
+    }
+
+    // Un nouvel état rend impossible de refaire ce qui a été annulé
+    pub fn record(&mut self, state: T) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.past.len() == self.limit {
+            self.past.pop_front();
+        }
+        self.past.push_back(state);
+        self.future.clear();
+    }
+
+    // Renvoie l'état précédent et retient `current` pour `redo`
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let state = self.past.pop_back()?;
+        self.future.push(current);
+        Some(state)
+    }
+
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let state = self.future.pop()?;
+        self.past.push_back(current);
+        Some(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Historique borné : les états les plus anciens sont oubliés
+    #[test]
+    fn test_limit() {
+        let mut history = History::new(2);
+        for state in 1..=4 {
+            history.record(state);
+        }
+        assert_eq!(history.undo(5), Some(4));
+        assert_eq!(history.undo(4), Some(3));
+        assert_eq!(history.undo(3), None);
+        assert_eq!(history.redo(3), Some(4));
+        assert_eq!(history.redo(4), Some(5));
+        assert_eq!(history.redo(5), None);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::compiler;
+use crate::debug::Debugger;
+use crate::debugger;
+use crate::dot;
+use crate::error::{Error, EvalError};
+use crate::heap::Heap;
+use crate::history::History;
+use crate::identifier::Identifier;
+use crate::namespace::NameSpace;
+use crate::namespacestack::NameSpaceStack;
+use crate::output;
+use crate::parser::{Parse, ParseError};
+use crate::parsing::function::NativeFunction;
+use crate::parsing::instruction::Instruction;
+use crate::parsing::program::Program;
+use crate::parsing::value::Value;
+use crate::r#type::Type;
+use crate::runner;
+use crate::typing::{self, TypeEnv};
+use crate::snapshot;
+use crate::trace;
+use crate::vm;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+// État d'une session : la pile des NameSpace, avec le NameSpace global, et son tas.
+// Les déclarations d'un appel à l'autre sont conservées, comme dans la boucle interactive.
+#[derive(Debug, Clone)]
+pub struct Interpreter {
+    nss: NameSpaceStack,
+    // État retenu par `checkpoint`, rétabli par `rollback`
+    checkpoint: Option<Box<NameSpaceStack>>,
+    // États retenus par `record`, parcourus par `undo` et `redo`
+    history: History<NameSpaceStack>,
+}
+
+// Nombre d'états retenus pour `undo`
+pub const HISTORY_LIMIT: usize = 100;
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut nss = NameSpaceStack::new();
+        nss.push(NameSpace::new());
+        Interpreter { nss, checkpoint: None, history: History::new(HISTORY_LIMIT) }
+    }
+
+    // Analyse un programme : des instructions séparées par des `;` ou des retours à la ligne
+    pub(crate) fn parse(&self, input: &str) -> Result<Program, ParseError> {
+        Program::parse(input)
+    }
+
+    // Exécute un programme déjà analysé et renvoie le résultat de sa dernière instruction
+    pub(crate) fn exec(&mut self, program: &Program) -> Result<(Option<Identifier>, Value), EvalError> {
+        program.exec(&mut self.nss)
+    }
+
+    pub(crate) fn exec_instr(&mut self, instr: &Instruction) -> Result<(Option<Identifier>, Value), EvalError> {
+        typing::check_branches(instr, &mut TypeEnv::new(), &self.nss)?;
+        instr.exec(&mut self.nss)
+    }
+
+    // Exécute un programme et renvoie le résultat de sa dernière instruction, avec le nom qu'elle a lié
+    pub fn eval_str(&mut self, input: &str) -> Result<(Option<String>, Value), Error> {
+        let program = self.parse(input)?;
+        Ok(named(self.exec(&program)?))
+    }
+
+    // Une seule instruction, comme une ligne de la boucle interactive
+    pub fn eval_instr(&mut self, input: &str) -> Result<(Option<String>, Value), Error> {
+        let instr = Instruction::parse(input)?;
+        Ok(named(self.exec_instr(&instr)?))
+    }
+
+    // Exécute un programme instruction par instruction : `f` reçoit le résultat de chacune
+    // et ce qu'elle a écrit avec `print!` ou `println!`. Une erreur d'évaluation n'arrête pas les suivantes.
+    pub fn eval_each(&mut self, input: &str, mut f: impl FnMut(&Self, Result<(Option<String>, Value), Error>, String)) -> Result<(), Error> {
+        let program = self.parse(input)?;
+        for instr in &program.0 {
+            let (res, text) = output::capture(|| self.exec_instr(instr));
+            f(self, res.map(named).map_err(Error::EvalError), text);
+        }
+        Ok(())
+    }
+
+    // Vrai pour une entrée sans instruction : des blancs et des commentaires
+    pub fn is_blank(&self, input: &str) -> bool {
+        self.parse(input).is_ok_and(|program| program.0.is_empty())
+    }
+
+    // Type d'une instruction, calculé sans l'évaluer
+    pub fn type_of(&self, input: &str) -> Result<Type, Error> {
+        let instr = Instruction::parse(input)?;
+        Ok(instr.type_of(&mut TypeEnv::new(), &self.nss)?)
+    }
+
+    // Exécute `f` en retenant ce qu'écrivent `print!` et `println!` au lieu de l'afficher
+    pub fn capture<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> (T, String) {
+        output::capture(|| f(self))
+    }
+
+    // Déclare une variable globale, visible des instructions évaluées ensuite
+    pub fn define(&mut self, id: &str, mutable: bool, value: Value) -> Result<(), Error> {
+        Ok(self.nss.declare(&Identifier::from(id), mutable, value)?)
+    }
+
+    // Enregistre une fonction de l'hôte, appelable comme une fonction globale.
+    // Les arguments sont vérifiés contre `params` (`Type::Infer` accepte tout) et le résultat contre `ret`.
+    pub fn register<F>(&mut self, name: &str, params: Vec<Type>, ret: Type, fun: F) -> Result<(), Error>
+    where F: Fn(&[Value]) -> Result<Value, String> + 'static {
+        let native = NativeFunction { name: Identifier::from(name), params, ret, fun: Box::new(fun) };
+        self.define(name, false, Value::Native(Rc::new(native)))
+    }
+
+    pub fn get(&self, id: &str) -> Result<Value, Error> {
+        Ok(self.nss.find(&Identifier::from(id))?)
+    }
+
+    pub(crate) fn heap(&self) -> &Heap {
+        self.nss.heap()
+    }
+
+    // Variables, fonctions et structures déclarées, pour la complétion
+    pub fn identifiers(&self) -> Vec<String> {
+        self.nss.identifiers().iter().map(|id| id.to_string()).collect()
+    }
+
+    // Les NameSpace de la pile, du global au plus récent
+    pub fn display_env(&self) -> String {
+        self.nss.to_string()
+    }
+
+    // Les cellules du tas, une par ligne
+    pub fn display_heap(&self) -> String {
+        self.heap().to_string()
+    }
+
+    // Schéma de la mémoire au format DOT de Graphviz
+    pub fn render_dot(&self) -> String {
+        dot::render(&self.nss, self.heap())
+    }
+
+    // Exécute un programme sur la machine à pile, indépendamment de l'état de la session.
+    // Un programme qu'elle ne sait pas encore traduire est refusé avant d'être exécuté.
+    pub fn run_vm(&self, input: &str) -> Result<Value, Error> {
+        let module = compiler::compile(&self.parse(input)?)?;
+        Ok(vm::run(&module)?)
+    }
+
+    // Exécute les fonctions `#[test]` d'un programme, chacune dans un interpréteur neuf,
+    // et écrit le compte rendu dans `out` : vrai si tous les tests passent
+    pub fn run_tests(input: &str, out: &mut impl Write) -> Result<bool, Error> {
+        let program = Program::parse(input)?;
+        Ok(runner::report(&program, out))
+    }
+
+    // Journal de l'évaluation, écrit dans `out` jusqu'à `stop_trace`
+    pub fn start_trace(&mut self, out: impl Write + 'static) {
+        trace::start(Box::new(out));
+    }
+
+    pub fn stop_trace(&mut self) {
+        trace::stop();
+    }
+
+    // Débogueur arrêté avant chaque instruction, qui lit ses commandes dans `input` et répond dans `out`
+    pub fn attach_debugger(&mut self, input: impl BufRead + 'static, out: impl Write + 'static) {
+        debugger::attach(Box::new(Debugger::new(input, out)));
+    }
+
+    pub fn detach_debugger(&mut self) -> bool {
+        debugger::detach().is_some()
+    }
+
+    // État complet de la session, au format de `snapshot`
+    pub fn save_state(&self) -> Result<String, String> {
+        snapshot::save(&self.nss, self.nss.heap())
+    }
+
+    // Remplace l'état de la session, ce que `undo` peut annuler ; en cas d'erreur, la session est inchangée
+    pub fn restore_state(&mut self, text: &str) -> Result<(), String> {
+        let (mut nss, heap) = snapshot::restore(text)?;
+        nss.set_heap(heap);
+        self.record();
+        self.nss = nss;
+        Ok(())
+    }
+
+    pub fn checkpoint(&mut self) {
+        self.checkpoint = Some(Box::new(self.nss.clone()));
+    }
+
+    // Le point de reprise est conservé : on peut y revenir plusieurs fois.
+    // Comme `restore_state`, un retour en arrière peut être annulé par `undo`.
+    pub fn rollback(&mut self) -> bool {
+        match self.checkpoint.clone() {
+            Some(state) => {
+                self.record();
+                self.nss = *state;
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn record(&mut self) {
+        self.history.record(self.nss.clone());
+    }
+
+    // Exécute `f` de façon à pouvoir l'annuler avec `undo`, s'il a modifié l'état de la session
+    pub fn undoable<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let before = self.nss.clone();
+        let res = f(self);
+        if self.nss.changes() != before.changes() || self.nss.heap().changes() != before.heap().changes() {
+            self.history.record(before);
+        }
+        res
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let current = self.nss.clone();
+        match self.history.undo(current) {
+            Some(state) => {
+                self.nss = state;
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let current = self.nss.clone();
+        match self.history.redo(current) {
+            Some(state) => {
+                self.nss = state;
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+fn named((id, value): (Option<Identifier>, Value)) -> (Option<String>, Value) {
+    (id.map(|id| id.to_string()), value)
+}
@@ -0,0 +1,32 @@
+// µRust comme bibliothèque : l'analyseur, l'évaluateur et l'`Interpreter` qui les réunit
+
+// LISTE DES MODULES
+mod parsing;
+mod parser;
+mod error;
+mod identifier;
+mod eval;
+mod namespace;
+mod namespacestack;
+mod r#type;
+mod heap;
+mod memory;
+mod memorycell;
+mod output;
+mod typing;
+mod interpreter;
+mod runner;
+mod debugger;
+mod debug;
+mod trace;
+mod dot;
+mod snapshot;
+mod history;
+mod bytecode;
+mod compiler;
+mod vm;
+
+pub use crate::error::Error;
+pub use crate::interpreter::Interpreter;
+pub use crate::parsing::value::Value;
+pub use crate::r#type::Type;
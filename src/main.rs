@@ -1,66 +1,388 @@
 // LISTE DES MODULES
-mod parsing;
-mod parser;
-mod error;
-mod identifier;
-mod eval;
-mod namespace;
-mod namespacestack;
-mod r#type;
-mod heap;
-mod memory;
-mod memorycell;
+mod command;
+mod editor;
 
 // LISTE DES IMPORTS
-use crate::r#type::Type;
-use crate::parsing::value::Value;
-use crate::error::{Error};
-use crate::identifier::Identifier;
-use std::io::{self, BufRead, Write};
-use crate::parsing::instruction::Instruction;
-use crate::namespacestack::NameSpaceStack;
-use crate::parser::Parse;
-
-// AFFICHAGE DU PROMPT
-fn prompt() {
-    print!("µRust # ");
-    // Permet de vider le buffer de sortie
-    io::stdout().flush().unwrap();
+use microrust::{Error, Interpreter, Type, Value};
+use rustyline::error::ReadlineError;
+use std::process::ExitCode;
+use crate::command::Command;
+use serde_json::json;
+use std::io::{self, Read};
+
+// PROMPTS, LE SECOND TANT QUE L'ENTRÉE N'EST PAS COMPLÈTE
+const PROMPT: &str = "µRust # ";
+const CONTINUATION_PROMPT: &str = "     .. ";
+
+// AIDE AFFICHÉE POUR DES ARGUMENTS INVALIDES
+const USAGE: &str = "Usage: microrust [--json | --debug] [--trace] [file.rs]\n       microrust --vm file.rs\n       microrust test file.rs";
+
+// Une entrée est complète quand toutes ses accolades et parenthèses sont refermées,
+// et qu'aucun commentaire `/* */` ni aucune chaîne n'est resté ouvert
+fn is_complete(input: &str) -> bool {
+    let mut depth = 0;
+    let mut comments = 0;
+    let mut string = false;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            // Le contenu d'une chaîne, `\"` compris, ne compte pas
+            ('\\', _) if string => {
+                chars.next();
+            }
+            ('"', _) if string => string = false,
+            _ if string => (),
+            ('/', Some('*')) => {
+                chars.next();
+                comments += 1;
+            }
+            ('*', Some('/')) if comments > 0 => {
+                chars.next();
+                comments -= 1;
+            }
+            _ if comments > 0 => (),
+            ('"', _) => string = true,
+            ('/', Some('/')) => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+            }
+            ('{', _) | ('(', _) => depth += 1,
+            ('}', _) | (')', _) => depth -= 1,
+            _ => (),
+        }
+    }
+    depth <= 0 && comments == 0 && !string
+}
+
+fn parse_exec(input: &str, interp: &mut Interpreter) -> Result<(Option<String>, Value), Error> {
+    interp.eval_instr(input)
+}
+
+// EXÉCUTION D'UN FICHIER COMPLET
+fn parse_exec_program(input: &str, interp: &mut Interpreter) -> Result<(Option<String>, Value), Error> {
+    interp.eval_str(input)
+}
+
+// EXÉCUTION PAR LA MACHINE À PILE, QUI REFUSE CE QU'ELLE NE SAIT PAS ENCORE TRADUIRE
+fn parse_run_vm(input: &str, interp: &mut Interpreter) -> Result<(Option<String>, Value), Error> {
+    Ok((None, interp.run_vm(input)?))
+}
+
+// VALEUR AFFICHÉE : UNE DÉCLARATION VAUT (), ON MONTRE ALORS LA VALEUR LIÉE À SON IDENTIFIANT
+fn shown_value(id: &Option<String>, val: &Value, interp: &Interpreter) -> Value {
+    id.as_ref()
+        .and_then(|id| interp.get(id).ok())
+        .unwrap_or_else(|| val.clone())
+}
+
+// AFFICHAGE D'UN RÉSULTAT
+fn display_result(id: Option<String>, val: &Value, interp: &Interpreter) -> String {
+    let val = &shown_value(&id, val, interp);
+    let id = id.as_deref().unwrap_or("-");
+    if *val == Value::Unit {
+        format!("{} : unit = ()", id)
+    } else {
+        format!("{} : {} = {}", id, Type::from(val), val)
+    }
+}
+
+// ÉVALUATION D'UNE ENTRÉE COMPLÈTE DE LA BOUCLE INTERACTIVE, RIEN POUR UNE ENTRÉE SANS CODE (BLANCS, COMMENTAIRES)
+fn eval_entry(input: &str, interp: &mut Interpreter) -> Option<String> {
+    if interp.is_blank(input) {
+        return None;
+    }
+    // Seules les instructions qui modifient l'état peuvent être annulées
+    Some(match interp.undoable(|interp| parse_exec(input.trim_end(), interp)) {
+        Ok((id, val)) => display_result(id, &val, interp),
+        Err(e) => e.report(),
+    })
+}
+
+// COMMANDES `:` DE LA BOUCLE INTERACTIVE
+fn run_command(line: &str, interp: &mut Interpreter) -> String {
+    let command = match Command::parse(line) {
+        Ok(command) => command,
+        Err(e) => return e.to_string(),
+    };
+    match command {
+        Command::Env => interp.display_env(),
+        Command::Heap => {
+            let heap = interp.display_heap();
+            if heap.is_empty() {
+                "Heap is empty.".to_string()
+            } else {
+                heap.trim_end().to_string()
+            }
+        }
+        // Le type est calculé par l'analyse statique, sans évaluer l'expression
+        Command::Type(input) => {
+            match interp.type_of(&input) {
+                Ok(t) => format!("- : {}", t),
+                Err(e) => e.report(),
+            }
+        }
+        Command::Reset => {
+            *interp = Interpreter::new();
+            "Session reset.".to_string()
+        }
+        Command::Load(path) => {
+            match std::fs::read_to_string(&path) {
+                Ok(input) => match interp.undoable(|interp| parse_exec_program(&input, interp)) {
+                    Ok((id, val)) => display_result(id, &val, interp),
+                    Err(e) => e.report(),
+                },
+                Err(e) => format!("Cannot read `{}`: {}", path, e),
+            }
+        }
+        Command::Dot(None) => interp.render_dot().trim_end().to_string(),
+        Command::Dot(Some(path)) => {
+            match std::fs::write(&path, interp.render_dot()) {
+                Ok(()) => format!("Memory diagram written to `{}`.", path),
+                Err(e) => format!("Cannot write `{}`: {}", path, e),
+            }
+        }
+        Command::Save(path) => {
+            match interp.save_state() {
+                Ok(state) => match std::fs::write(&path, state) {
+                    Ok(()) => format!("State saved to `{}`.", path),
+                    Err(e) => format!("Cannot write `{}`: {}", path, e),
+                },
+                Err(e) => format!("Cannot save the state: {}.", e),
+            }
+        }
+        Command::Restore(path) => {
+            match std::fs::read_to_string(&path) {
+                Ok(state) => match interp.restore_state(&state) {
+                    Ok(()) => format!("State restored from `{}`.", path),
+                    Err(e) => format!("Cannot restore `{}`: {}.", path, e),
+                },
+                Err(e) => format!("Cannot read `{}`: {}", path, e),
+            }
+        }
+        Command::Checkpoint => {
+            interp.checkpoint();
+            "Checkpoint saved.".to_string()
+        }
+        Command::Rollback => {
+            if interp.rollback() {
+                "Rolled back to the checkpoint.".to_string()
+            } else {
+                "No checkpoint to roll back to.".to_string()
+            }
+        }
+        Command::Undo => {
+            if interp.undo() {
+                "Undone.".to_string()
+            } else {
+                "Nothing to undo.".to_string()
+            }
+        }
+        Command::Redo => {
+            if interp.redo() {
+                "Redone.".to_string()
+            } else {
+                "Nothing to redo.".to_string()
+            }
+        }
+    }
 }
 
-fn parse_exec(input: &str, nss: &mut NameSpaceStack) -> Result<(Option<Identifier>, Value), Error> {
-    match Instruction::parse(input) {
-        Ok(instr) => {
-            instr.exec(nss).map_err(|err| Error::EvalError(err))
+// LECTURE D'UN FICHIER, OU DE L'ENTRÉE STANDARD SANS CHEMIN
+fn read_input(path: Option<&str>) -> Result<String, ExitCode> {
+    let input = match path {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| format!("Cannot read `{}`: {}", path, e)),
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input).map(|_| input).map_err(|e| format!("Cannot read the standard input: {}", e))
+        }
+    };
+    input.map_err(|e| {
+        eprintln!("{}", e);
+        ExitCode::from(2)
+    })
+}
+
+// MODE SCRIPT : `microrust fichier.rs`, PAR LA MACHINE À PILE AVEC `--vm`
+fn run_script(path: &str, interp: &mut Interpreter, vm: bool) -> ExitCode {
+    let input = match read_input(Some(path)) {
+        Ok(input) => input,
+        Err(code) => return code,
+    };
+    let res = if vm { parse_run_vm(&input, interp) } else { parse_exec_program(&input, interp) };
+    match res {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e.report());
+            exit_code(&e)
+        }
+    }
+}
+
+// CODE DE SORTIE D'UN PROGRAMME EN ERREUR : 2 POUR L'ANALYSE OU LA TRADUCTION POUR LA MACHINE À PILE, 101 POUR UN `panic!` (COMME RUST), 1 SINON
+fn exit_code(e: &Error) -> ExitCode {
+    match e {
+        Error::ParseError(_) | Error::CompileError(_) => ExitCode::from(2),
+        Error::EvalError(_) if e.kind() == "Panic" => ExitCode::from(101),
+        Error::EvalError(_) => ExitCode::from(1),
+    }
+}
+
+// RÉSULTAT D'UNE INSTRUCTION EN JSON
+fn json_result(res: &Result<(Option<String>, Value), Error>, output: &str, interp: &Interpreter) -> serde_json::Value {
+    match res {
+        Ok((id, val)) => {
+            let val = shown_value(id, val, interp);
+            json!({
+                "id": id.as_ref().map(|id| id.to_string()),
+                "type": Type::from(&val).to_string(),
+                "value": val.to_string(),
+                "output": output,
+                "error": null,
+                "message": null,
+            })
+        },
+        Err(e) => {
+            let message = match e {
+                Error::ParseError(e) => e.to_string(),
+                Error::EvalError(e) => e.to_string(),
+                Error::CompileError(e) => e.to_string(),
+            };
+            json!({"id": null, "type": null, "value": null, "output": output, "error": e.kind(), "message": message})
+        }
+    }
+}
+
+// MODE `--json` : un objet JSON par ligne et par instruction.
+// Une erreur d'évaluation n'arrête pas les instructions suivantes.
+fn run_json(input: &str, interp: &mut Interpreter, out: &mut impl io::Write) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+    // Ce qu'écrit chaque instruction est joint à son objet plutôt que mêlé aux lignes JSON
+    let res = interp.eval_each(input, |interp, res, text| {
+        // La première erreur décide du code de sortie
+        match &res {
+            Err(e) if code == ExitCode::SUCCESS => code = exit_code(e),
+            _ => (),
+        }
+        writeln!(out, "{}", json_result(&res, &text, interp)).unwrap();
+    });
+    match res {
+        Ok(()) => code,
+        Err(e) => {
+            writeln!(out, "{}", json_result(&Err(e), "", interp)).unwrap();
+            ExitCode::from(2)
+        }
+    }
+}
+
+// MODE `test` : les fonctions `#[test]` du fichier, chacune dans un interpréteur neuf, avec un résumé comme `cargo test`
+fn run_tests(input: &str, out: &mut impl io::Write) -> ExitCode {
+    match Interpreter::run_tests(input, out) {
+        Ok(true) => ExitCode::SUCCESS,
+        // Comme `cargo test`
+        Ok(false) => ExitCode::from(101),
+        Err(e) => {
+            writeln!(out, "{}", e.report()).unwrap();
+            ExitCode::from(2)
         }
-        Err(e) => Err(Error::ParseError(e)),
     }
 }
 
 // FONCTION PRINCIPALE
-fn main(){
-    prompt();
-    let ns = namespace::NameSpace::new();
-    let mut nss = NameSpaceStack::new();
-    nss.push(ns);
-
-    for line in io::stdin().lock().lines() {
-        let line = line.unwrap();
-
-        match parse_exec(&line, &mut nss) {
-            Ok((id, val)) => {
-                if val == Value::Unit {
-                    println!("{} : unit = ()", id.unwrap_or(Identifier::from("-")));
-                } else {
-                    println!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val);
-                }
+fn main() -> ExitCode {
+    let mut interp = Interpreter::new();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(flag) = args.iter().find(|arg| arg.starts_with("--") && !["--json", "--debug", "--trace", "--vm"].contains(&arg.as_str())) {
+        eprintln!("Unknown option `{}`.\n{}", flag, USAGE);
+        return ExitCode::from(2);
+    }
+    // Seul l'évaluateur tient le journal de `--trace`
+    if args.iter().any(|arg| arg == "--vm") && args.iter().any(|arg| arg == "--trace") {
+        eprintln!("`--vm` cannot be used with `--trace`.\n{}", USAGE);
+        return ExitCode::from(2);
+    }
+    // Le journal de l'évaluation va sur la sortie d'erreur, à côté des sorties du programme
+    if args.iter().any(|arg| arg == "--trace") {
+        interp.start_trace(io::stderr());
+    }
+    if args.first().map(String::as_str) == Some("test") {
+        return match args.get(1) {
+            Some(path) => match read_input(Some(path)) {
+                Ok(input) => run_tests(&input, &mut io::stdout()),
+                Err(code) => code,
+            },
+            None => {
+                eprintln!("Missing file.\n{}", USAGE);
+                ExitCode::from(2)
+            }
+        };
+    }
+    let path = args.iter().find(|arg| !arg.starts_with("--")).map(String::as_str);
+    if args.iter().any(|arg| arg == "--json") {
+        return match read_input(path) {
+            Ok(input) => run_json(&input, &mut interp, &mut io::stdout()),
+            Err(code) => code,
+        };
+    }
+    // Les commandes du débogueur sont lues sur l'entrée standard, le programme vient donc d'un fichier
+    if args.iter().any(|arg| arg == "--debug") {
+        let Some(path) = path else {
+            eprintln!("Missing file.\n{}", USAGE);
+            return ExitCode::from(2);
+        };
+        interp.attach_debugger(io::stdin().lock(), io::stdout());
+        return run_script(path, &mut interp, false);
+    }
+    if let Some(path) = path {
+        return run_script(path, &mut interp, args.iter().any(|arg| arg == "--vm"));
+    }
+
+    let mut editor = match editor::new_editor() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("Cannot start the line editor: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let history = editor::history_path();
+    if let Some(path) = &history {
+        // Pas d'historique à la première session
+        let _ = editor.load_history(path);
+    }
+
+    let mut input = String::new();
+    loop {
+        let line = match editor.readline(if input.is_empty() { PROMPT } else { CONTINUATION_PROMPT }) {
+            Ok(line) => line,
+            // Ctrl-C abandonne l'entrée en cours, Ctrl-D quitte
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue;
             }
-            Err(e) => {
-                println!("{}", e);
+            Err(_) => break,
+        };
+        if input.is_empty() && line.trim_start().starts_with(':') {
+            let _ = editor.add_history_entry(line.trim());
+            println!("{}", run_command(&line, &mut interp));
+        } else {
+            input.push_str(&line);
+            input.push('\n');
+            if !is_complete(&input) {
+                continue;
             }
+            let _ = editor.add_history_entry(input.trim_end());
+            if let Some(res) = eval_entry(&input, &mut interp) {
+                println!("{}", res);
+            }
+            input.clear();
+        }
+        if let Some(helper) = editor.helper_mut() {
+            helper.set_identifiers(interp.identifiers());
+        }
+        if let Some(path) = &history {
+            let _ = editor.save_history(path);
         }
-        prompt();
     }
+    ExitCode::SUCCESS
 }
 
 // Tests de parse_exec
@@ -68,495 +390,1059 @@ fn main(){
 mod tests {
     use super::*;
 
+    // Sortie de la boucle principale pour une ligne
+    fn run(input: &str, interp: &mut Interpreter) -> String {
+        match parse_exec(input, interp) {
+            Ok((id, val)) => display_result(id, &val, interp),
+            Err(e) => format!("{}", e),
+        }
+    }
+
+    fn new_nss() -> Interpreter {
+        Interpreter::new()
+    }
+
+    // Session de la boucle interactive sur les lignes d'un fichier, chaque ligne précédée de son prompt
+    fn transcript(input: &str) -> String {
+        let mut interp = new_nss();
+        let mut out = String::new();
+        let mut entry = String::new();
+        for line in input.lines() {
+            let prompt = if entry.is_empty() { PROMPT } else { CONTINUATION_PROMPT };
+            out.push_str(format!("{}{}", prompt, line).trim_end());
+            out.push('\n');
+            if entry.is_empty() && line.trim_start().starts_with(':') {
+                out.push_str(&run_command(line, &mut interp));
+                out.push('\n');
+                continue;
+            }
+            entry.push_str(line);
+            entry.push('\n');
+            if !is_complete(&entry) {
+                continue;
+            }
+            let (res, printed) = interp.capture(|interp| eval_entry(&entry, interp));
+            out.push_str(&printed);
+            if let Some(res) = res {
+                out.push_str(&res);
+                out.push('\n');
+            }
+            entry.clear();
+        }
+        out
+    }
+
+    // Lignes retirées (`-`) et ajoutées (`+`) d'après la plus longue sous-suite commune
+    fn diff(expected: &str, actual: &str) -> String {
+        let (a, b): (Vec<&str>, Vec<&str>) = (expected.lines().collect(), actual.lines().collect());
+        let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+            }
+        }
+        let mut out = String::new();
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                i += 1;
+                j += 1;
+            } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                out.push_str(&format!("{:>4} - {}\n", i + 1, a[i]));
+                i += 1;
+            } else {
+                out.push_str(&format!("{:>4} + {}\n", i + 1, b[j]));
+                j += 1;
+            }
+        }
+        out
+    }
+
     #[test]
     fn test() {
-        let mut nss = NameSpaceStack::new();
-        let ns = namespace::NameSpace::new();
-        nss.push(ns);
+        let mut interp = Interpreter::new();
 
         // 1 + 1 => - : isize = 2
-        let r = parse_exec("1 + 1", &mut nss);
+        let r = parse_exec("1 + 1", &mut interp);
         let s2 = "- : isize = 2";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // 1 + 2 - 3 * 4 / 5 => - : isize = 1
-        let r = parse_exec("1 + 2 - 3 * 4 / 5", &mut nss);
+        let r = parse_exec("1 + 2 - 3 * 4 / 5", &mut interp);
         let s2 = "- : isize = 1";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // let un = 1 => un : isize = 1
-        let r = parse_exec("let un = 1", &mut nss);
+        let r = parse_exec("let un = 1", &mut interp);
         let s2 = "un : isize = 1";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // un => - : isize = 1
-        let r = parse_exec("un", &mut nss);
+        let r = parse_exec("un", &mut interp);
         let s2 = "- : isize = 1";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // un + 1 => - : isize = 2
-        let r = parse_exec("un + 1", &mut nss);
+        let r = parse_exec("un + 1", &mut interp);
         let s2 = "- : isize = 2";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // 1 / (1 - 1) => Evaluation Error: Division by zero, `(1 - 1)` evaluates to 0
-        let r = parse_exec("1 / (1 - 1)", &mut nss);
+        let r = parse_exec("1 / (1 - 1)", &mut interp);
         let s2 = "Evaluation Error: Division by zero, `(1 - 1)` evaluates to 0";
         if let Err(e) = r {
             assert_eq!(format!("{}", e), s2);
         }
 
         // let repete = 0 => repete : isize = 0
-        let r = parse_exec("let repete = 0", &mut nss);
+        let r = parse_exec("let repete = 0", &mut interp);
         let s2 = "repete : isize = 0";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // let repete = 0 => Evaluation Error: Identifier `repete` already defined.
-        let r = parse_exec("let repete = 0", &mut nss);
+        let r = parse_exec("let repete = 0", &mut interp);
         let s2 = "Evaluation Error: Identifier `repete` already defined.";
         if let Err(e) = r {
             assert_eq!(format!("{}", e), s2);
         }
 
         // let pasdefini = 1 / 0 => Evaluation Error: Division by zero, `0' evaluates to 0
-        let r = parse_exec("let pasdefini = 1 / 0", &mut nss);
+        let r = parse_exec("let pasdefini = 1 / 0", &mut interp);
         let s2 = "Evaluation Error: Division by zero, `0` evaluates to 0";
         if let Err(e) = r {
             assert_eq!(format!("{}", e), s2);
         }
 
         // let pasdefini = toujourspasdefini => Evaluation Error: Undefined identifier `toujourspasdefini`.
-        let r = parse_exec("let pasdefini = toujourspasdefini", &mut nss);
+        let r = parse_exec("let pasdefini = toujourspasdefini", &mut interp);
         let s2 = "Evaluation Error: Undefined identifier `toujourspasdefini`.";
         if let Err(e) = r {
             assert_eq!(format!("{}", e), s2);
         }
 
         // pasdefini => Evaluation Error: Undefined identifier `pasdefini`.
-        let r = parse_exec("pasdefini", &mut nss);
+        let r = parse_exec("pasdefini", &mut interp);
         let s2 = "Evaluation Error: Undefined identifier `pasdefini`.";
         if let Err(e) = r {
             assert_eq!(format!("{}", e), s2);
         }
 
         // let x = 0 => x : isize = 0
-        let r = parse_exec("let x = 0", &mut nss);
+        let r = parse_exec("let x = 0", &mut interp);
         let s2 = "x : isize = 0";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // {let x = 1; x+1} => - : isize = 2
-        let r = parse_exec("{let x = 1; x+1}", &mut nss);
+        let r = parse_exec("{let x = 1; x+1}", &mut interp);
         let s2 = "- : isize = 2";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // x + 1 => - : isize = 1
-        let r = parse_exec("x + 1", &mut nss);
+        let r = parse_exec("x + 1", &mut interp);
         let s2 = "- : isize = 1";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
-        // {let tmp = 0} => - : isize = 0
-        let r = parse_exec("{let tmp = 0}", &mut nss);
-        let s2 = "- : isize = 0";
+        // {let tmp = 0} => - : unit = ()
+        let r = parse_exec("{let tmp = 0}", &mut interp);
+        let s2 = "- : unit = ()";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // tmp => Evaluation Error: Undefined identifier `tmp`.
-        let r = parse_exec("tmp", &mut nss);
+        let r = parse_exec("tmp", &mut interp);
         let s2 = "Evaluation Error: Undefined identifier `tmp`.";
         if let Err(e) = r {
             assert_eq!(format!("{}", e), s2);
         }
 
         // 0 == 0 => - : bool = true
-        let r = parse_exec("0 == 0", &mut nss);
+        let r = parse_exec("0 == 0", &mut interp);
         let s2 = "- : bool = true";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // 1 - 1 != 1 + 1 => - : bool = true
-        let r = parse_exec("1 - 1 != 1 + 1", &mut nss);
+        let r = parse_exec("1 - 1 != 1 + 1", &mut interp);
         let s2 = "- : bool = true";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // true == false => - : bool = false
-        let r = parse_exec("true == false", &mut nss);
+        let r = parse_exec("true == false", &mut interp);
         let s2 = "- : bool = false";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // true == 1 => Evaluation Error: Type mismatch in expression `1`. Expected: bool. Found: isize
-        let r = parse_exec("true == 1", &mut nss);
+        let r = parse_exec("true == 1", &mut interp);
         let s2 = "Evaluation Error: Type mismatch in expression `1`. Expected: bool. Found: isize";
         if let Err(e) = r {
             assert_eq!(format!("{}", e), s2);
         }
 
         // true != 1 => Evaluation Error: Type mismatch in expression `1`. Expected: bool. Found: isize
-        let r = parse_exec("true != 1", &mut nss);
+        let r = parse_exec("true != 1", &mut interp);
         let s2 = "Evaluation Error: Type mismatch in expression `1`. Expected: bool. Found: isize";
         if let Err(e) = r {
             assert_eq!(format!("{}", e), s2);
         }
 
         // true == (1 == 1) => - : bool = true
-        let r = parse_exec("true == (1 == 1)", &mut nss);
+        let r = parse_exec("true == (1 == 1)", &mut interp);
         let s2 = "- : bool = true";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // 0 < 0  => - : bool = false
-        let r = parse_exec("0 < 0", &mut nss);
+        let r = parse_exec("0 < 0", &mut interp);
         let s2 = "- : bool = false";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // 1 - 1 >= un + 1
-        let r = parse_exec("1 - 1 >= un + 1", &mut nss);
+        let r = parse_exec("1 - 1 >= un + 1", &mut interp);
         let s2 = "- : bool = false";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // true > false => Evaluation Error: Type mismatch in expression `true`. Expected: isize. Found: bool
-        let r = parse_exec("true > false", &mut nss);
+        let r = parse_exec("true > false", &mut interp);
         let s2 = "Evaluation Error: Type mismatch in expression `true`. Expected: isize. Found: bool";
         if let Err(e) = r {
             assert_eq!(format!("{}", e), s2);
         }
 
         // true || false && un == 1
-        let r = parse_exec("true || false && un == 1", &mut nss);
+        let r = parse_exec("true || false && un == 1", &mut interp);
         let s2 = "- : bool = true";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // x == 0 || 1 / x == 1 => - : bool = true
-        let r = parse_exec("x == 0 || 1 / x == 1", &mut nss);
+        let r = parse_exec("x == 0 || 1 / x == 1", &mut interp);
         let s2 = "- : bool = true";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // 1 / x == 1 || x == 0 => Evaluation Error: Division by zero, `x` evaluates to 0
-        let r = parse_exec("1 / x == 1 || x == 0", &mut nss);
+        let r = parse_exec("1 / x == 1 || x == 0", &mut interp);
         let s2 = "Evaluation Error: Division by zero, `x` evaluates to 0";
         if let Err(e) = r {
             assert_eq!(format!("{}", e), s2);
         }
 
         // (un == 1) ? 1 : 2 => - : isize = 1
-        let r = parse_exec("(un == 1) ? 1 : 2", &mut nss);
+        let r = parse_exec("(un == 1) ? 1 : 2", &mut interp);
         let s2 = "- : isize = 1";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // (un != 1) ? 1 : 2 => - : isize = 2
-        let r = parse_exec("(un != 1) ? 1 : 2", &mut nss);
+        let r = parse_exec("(un != 1) ? 1 : 2", &mut interp);
         let s2 = "- : isize = 2";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // let mut acc = 0
-        let r = parse_exec("let mut acc = 0", &mut nss);
+        let r = parse_exec("let mut acc = 0", &mut interp);
         let s2 = "acc : isize = 0";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
-        // acc = acc + 1 => - : isize = 1
-        let r = parse_exec("acc = acc + 1", &mut nss);
-        let s2 = "- : isize = 1";
+        // acc = acc + 1 => - : unit = ()
+        let r = parse_exec("acc = acc + 1", &mut interp);
+        let s2 = "- : unit = ()";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // acc => - : isize = 1
-        let r = parse_exec("acc", &mut nss);
+        let r = parse_exec("acc", &mut interp);
         let s2 = "- : isize = 1";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // {let mut flag = false; flag = flag || x == 0; flag} => - : bool = true
-        let r = parse_exec("{let mut flag = false; flag = flag || x == 0; flag}", &mut nss);
+        let r = parse_exec("{let mut flag = false; flag = flag || x == 0; flag}", &mut interp);
         let s2 = "- : bool = true";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // {let mut mayfreeze = 0; {let mayfreeze = 1; mayfreeze = 2}} => Evaluation Error: Identifier `mayfreeze` is not mutable.
-        let r = parse_exec("{let mut mayfreeze = 0; {let mayfreeze = 1; mayfreeze = 2}}", &mut nss);
+        let r = parse_exec("{let mut mayfreeze = 0; {let mayfreeze = 1; mayfreeze = 2}}", &mut interp);
         let s2 = "Evaluation Error: Value at `mayfreeze` is not mutable.";
         if let Err(e) = r {
             assert_eq!(format!("{}", e), s2);
         }
 
-        // {let maymelt = 0; {let mut maymelt = 0; maymelt = 1}} => - : isize = 1
-        let r = parse_exec("{let maymelt = 0; {let mut maymelt = 0; maymelt = 1}}", &mut nss);
-        let s2 = "- : isize = 1";
+        // {let maymelt = 0; {let mut maymelt = 0; maymelt = 1}} => - : unit = ()
+        let r = parse_exec("{let maymelt = 0; {let mut maymelt = 0; maymelt = 1}}", &mut interp);
+        let s2 = "- : unit = ()";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
-        // if (acc == 0) { acc = acc + 1} else { acc = acc - 1} => - : isize = 0
-        let r = parse_exec("if (acc == 0) { acc = acc + 1} else { acc = acc - 1}", &mut nss);
-        let s2 = "- : isize = 0";
+        // if (acc == 0) { acc = acc + 1} else { acc = acc - 1} => - : unit = ()
+        let r = parse_exec("if (acc == 0) { acc = acc + 1} else { acc = acc - 1}", &mut interp);
+        let s2 = "- : unit = ()";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // acc => - : isize = 0
-        let r = parse_exec("acc", &mut nss);
+        let r = parse_exec("acc", &mut interp);
         let s2 = "- : isize = 0";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // let mut k = 0 => k : isize = 0
-        let r = parse_exec("let mut k = 0", &mut nss);
+        let r = parse_exec("let mut k = 0", &mut interp);
         let s2 = "k : isize = 0";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // while (k < 4) {k = k + 3} => - : unit = ()
-        let r = parse_exec("while (k < 4) {k = k + 3}", &mut nss);
+        let r = parse_exec("while (k < 4) {k = k + 3}", &mut interp);
         let s2 = "- : unit = ()";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // k => - : isize = 6
-        let r = parse_exec("k", &mut nss);
+        let r = parse_exec("k", &mut interp);
         let s2 = "- : isize = 6";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // let i = 0 => i : isize = 0
-        let r = parse_exec("let i = 0", &mut nss);
+        let r = parse_exec("let i = 0", &mut interp);
         let s2 = "i : isize = 0";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // &i => - : Ptr = @[0, i]
-        let r = parse_exec("&i", &mut nss);
+        let r = parse_exec("&i", &mut interp);
         let s2 = "- : Ptr = @[0, i]";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // {let i = 8; &i} => - : Ptr = @[1, i]
-        let r = parse_exec("{let i = 8; &i}", &mut nss);
+        let r = parse_exec("{let i = 8; &i}", &mut interp);
         let s2 = "- : Ptr = @[1, i]";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
-
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
-            }
+            assert_eq!(display_result(id, &val, &interp), s2);
         }
 
         // {let y = 8; &i} => - : Ptr = @[0, i]
-        let r = parse_exec("{let y = 8; &i}", &mut nss);
+        let r = parse_exec("{let y = 8; &i}", &mut interp);
         let s2 = "- : Ptr = @[0, i]";
         if let Ok((id, val)) = r {
-            if val == Value::Unit {
-                assert_eq!(format!("{} : unit = ()", id.unwrap_or(Identifier::from("-"))), s2)
+            assert_eq!(display_result(id, &val, &interp), s2);
+        }
+    }
 
-            } else {
-                assert_eq!(format!("{} : {} = {}", id.unwrap_or(Identifier::from("-")), Type::from(&val), val), s2);
+    #[test]
+    fn test_methods() {
+        let mut interp = new_nss();
+        assert_eq!(run("struct Point { x: isize, y: isize }", &mut interp), "Point : unit = ()");
+        assert_eq!(run("impl Point { fn new(x: isize, y: isize) -> Point { Point { x: x, y: y } } fn norm(&self) -> isize { self.x * self.x + self.y * self.y } }", &mut interp), "- : unit = ()");
+        assert_eq!(run("impl Point { fn translate(&mut self, dx: isize) { self.x = self.x + dx } fn reset(&self) { self.x = 0 } }", &mut interp), "- : unit = ()");
+        assert_eq!(run("let p = Point::new(3, 4)", &mut interp), "p : Point = Point { x: 3, y: 4 }");
+        assert_eq!(run("p.norm()", &mut interp), "- : isize = 25");
+        assert_eq!(run("Point::norm(&p)", &mut interp), "- : isize = 25");
+        assert_eq!(run("p.translate(1)", &mut interp), "Evaluation Error: Value at `p` is not mutable.");
+        assert_eq!(run("let mut q = Point { y: 1, x: 2 }", &mut interp), "q : Point = Point { x: 2, y: 1 }");
+        assert_eq!(run("q.translate(5)", &mut interp), "- : unit = ()");
+        assert_eq!(run("q.x", &mut interp), "- : isize = 7");
+        assert_eq!(run("q.reset()", &mut interp), "Evaluation Error: Value at `self` is not mutable.");
+        assert_eq!(run("q.y = 9", &mut interp), "- : unit = ()");
+        assert_eq!(run("q", &mut interp), "- : Point = Point { x: 7, y: 9 }");
+        assert_eq!(run("p.size()", &mut interp), "Evaluation Error: No method named `size` found for type `Point`.");
+        assert_eq!(run("Point { x: 1 }", &mut interp), "Evaluation Error: Missing field `y` in initializer of `Point`.");
+        assert_eq!(run("Point::new(true, 1)", &mut interp), "Evaluation Error: Type mismatch in expression `true`. Expected: isize. Found: bool");
+        assert_eq!(run("Point::new(1)", &mut interp), "Evaluation Error: Function `new` takes 2 argument(s) but 1 were supplied.");
+        // Un receveur temporaire est rangé dans une cellule le temps de l'appel
+        assert_eq!(run("Point { x: 3, y: 4 }.norm()", &mut interp), "- : isize = 25");
+        assert_eq!(run("Point::new(1, 2).translate(1)", &mut interp), "- : unit = ()");
+        assert_eq!(run("impl Point { fn ratio(&self) -> isize { self.x / self.y } }", &mut interp), "- : unit = ()");
+        assert!(run("Point { x: 1, y: 0 }.ratio()", &mut interp).starts_with("Evaluation Error: Division by zero"));
+        assert!(!interp.display_env().contains("[1]"));
+        // Un champ comme receveur : `&mut self` modifie le champ lui-même
+        assert_eq!(run("struct Line { a: Point, b: Point }", &mut interp), "Line : unit = ()");
+        assert_eq!(run("let mut l = Line { a: Point { x: 3, y: 4 }, b: Point { x: 0, y: 1 } }", &mut interp), "l : Line = Line { a: Point { x: 3, y: 4 }, b: Point { x: 0, y: 1 } }");
+        assert_eq!(run("l.a.norm()", &mut interp), "- : isize = 25");
+        assert_eq!(run("l.b.translate(2)", &mut interp), "- : unit = ()");
+        assert_eq!(run("l.b.x", &mut interp), "- : isize = 2");
+        assert_eq!(run("let m = l", &mut interp), "m : Line = Line { a: Point { x: 3, y: 4 }, b: Point { x: 2, y: 1 } }");
+        assert_eq!(run("m.a.translate(1)", &mut interp), "Evaluation Error: Value at `m.a` is not mutable.");
+        // Un pointeur renvoyé par un appel désigne le receveur, et l'appel n'est fait qu'une fois
+        assert_eq!(run("let mut calls = 0", &mut interp), "calls : isize = 0");
+        assert_eq!(run("fn at(p: &Point) -> &Point { calls = calls + 1; p }", &mut interp), "at : fn(Ptr) -> Ptr = <fn at>");
+        assert_eq!(run("at(&q).translate(3)", &mut interp), "- : unit = ()");
+        assert_eq!(run("q.x", &mut interp), "- : isize = 10");
+        assert_eq!(run("at(&q).norm()", &mut interp), "- : isize = 181");
+        assert_eq!(run("calls", &mut interp), "- : isize = 2");
+    }
+
+    #[test]
+    fn test_closures() {
+        let mut interp = new_nss();
+        assert_eq!(run("let y = 10", &mut interp), "y : isize = 10");
+        assert_eq!(run("let add = |x| x + y", &mut interp), "add : fn(_) -> _ = <closure>");
+        assert_eq!(run("add(1)", &mut interp), "- : isize = 11");
+        assert_eq!(run("let addm = move |x: isize| -> isize { x + y }", &mut interp), "addm : fn(isize) -> isize = <closure>");
+        assert_eq!(run("addm(true)", &mut interp), "Evaluation Error: Type mismatch in expression `true`. Expected: isize. Found: bool");
+        assert_eq!(run("let twice = |h, v| h(h(v))", &mut interp), "twice : fn(_, _) -> _ = <closure>");
+        assert_eq!(run("twice(add, 1)", &mut interp), "- : isize = 21");
+        assert_eq!(run("let mut f = |x| x", &mut interp), "f : fn(_) -> _ = <closure>");
+        assert_eq!(run("{let z = 1; f = |x| x + z; f(1)}", &mut interp), "- : isize = 2");
+        assert_eq!(run("f(1)", &mut interp), "Evaluation Error: Dangling reference to `z`, the block where it was declared has ended.");
+        assert_eq!(run("{let z = 100; f(1)}", &mut interp), "Evaluation Error: Dangling reference to `z`, the block where it was declared has ended.");
+        assert_eq!(run("{let z = 1; f = move |x| x + z}", &mut interp), "- : unit = ()");
+        assert_eq!(run("f(1)", &mut interp), "- : isize = 2");
+    }
+
+    #[test]
+    fn test_for_loops() {
+        let mut interp = new_nss();
+        assert_eq!(run("0..5", &mut interp), "- : Range = 0..5");
+        assert_eq!(run("1..=3", &mut interp), "- : Range = 1..4");
+        assert_eq!(run("let mut s = 0", &mut interp), "s : isize = 0");
+        assert_eq!(run("for i in 0..5 { s = s + i }", &mut interp), "- : unit = ()");
+        assert_eq!(run("s", &mut interp), "- : isize = 10");
+        assert_eq!(run("for i in 1..=10 { if (i % 2 == 0) { continue } else {}; if (i > 7) { break } else {}; s = s + i }", &mut interp), "- : unit = ()");
+        assert_eq!(run("s", &mut interp), "- : isize = 26");
+        assert_eq!(run("i", &mut interp), "Evaluation Error: Undefined identifier `i`.");
+        assert_eq!(run("for i in 0..3 { i = 5 }", &mut interp), "Evaluation Error: Value at `i` is not mutable.");
+        assert_eq!(run("for i in true {}", &mut interp), "Evaluation Error: Type mismatch in expression `true`. Expected: Range. Found: bool");
+        assert_eq!(run("let mut k = 0", &mut interp), "k : isize = 0");
+        assert_eq!(run("while (true) { k = k + 1; if (k == 3) { break } else {} }", &mut interp), "- : unit = ()");
+        assert_eq!(run("k", &mut interp), "- : isize = 3");
+        assert_eq!(run("break", &mut interp), "Evaluation Error: `break` outside of a loop.");
+        assert_eq!(run("fn f() { continue }", &mut interp), "f : fn() -> unit = <fn f>");
+        assert_eq!(run("for i in 0..3 { f() }", &mut interp), "Evaluation Error: `continue` outside of a loop.");
+    }
+
+    #[test]
+    fn test_if_expressions() {
+        let mut interp = new_nss();
+        assert_eq!(run("let mut y = 0", &mut interp), "y : isize = 0");
+        assert_eq!(run("if y == 0 { y = 1 }", &mut interp), "- : unit = ()");
+        assert_eq!(run("y", &mut interp), "- : isize = 1");
+        assert_eq!(run("let v = if y == 1 { 10 } else { 20 }", &mut interp), "v : isize = 10");
+        assert_eq!(run("let w = if y == 0 { 1 } else if y == 1 { 2 } else { 3 }", &mut interp), "w : isize = 2");
+        assert_eq!(run("if y == 1 { let z = 5; z + 1 } else { 0 }", &mut interp), "- : isize = 6");
+        assert_eq!(run("if y == 1 { 1 } else { true }", &mut interp), "Evaluation Error: `if` and `else` have incompatible types. Expected: isize. Found: bool");
+        assert_eq!(run("if y == 1 { 1 } else if y == 2 { 2 } else { false }", &mut interp), "Evaluation Error: `if` and `else` have incompatible types. Expected: isize. Found: bool");
+        assert_eq!(run("if 1 { 2 } else { 3 }", &mut interp), "Evaluation Error: Type mismatch in expression `1`. Expected: bool. Found: isize");
+        assert_eq!(run("(if y == 1 { 3 } else { 4 }) * 2", &mut interp), "- : isize = 6");
+        // Sans `else`, la branche doit valoir (), ce qui est vérifié à la déclaration, avant toute exécution
+        assert_eq!(run("if y == 1 { 1 }", &mut interp), "Evaluation Error: `if` may be missing an `else` clause. Expected: unit. Found: isize");
+        assert_eq!(run("if y == 0 { 1 } else if y == 2 { 2 }", &mut interp), "Evaluation Error: `if` may be missing an `else` clause. Expected: unit. Found: isize");
+        assert_eq!(run("fn g(b: bool) -> isize { if b { 1 } else { false } }", &mut interp), "Evaluation Error: `if` and `else` have incompatible types. Expected: isize. Found: bool");
+        assert_eq!(run("g(true)", &mut interp), "Evaluation Error: Undefined identifier `g`.");
+        assert_eq!(run("{ println!(\"before\"); if y == 1 { 1 } }", &mut interp), "Evaluation Error: `if` may be missing an `else` clause. Expected: unit. Found: isize");
+        assert_eq!(run("fn h(n: isize) -> isize { let mut k = 0; while k < n { k = if k < 3 { k + 1 } else { k + 2 } }; k }", &mut interp), "h : fn(isize) -> isize = <fn h>");
+        assert_eq!(run("h(6)", &mut interp), "- : isize = 7");
+    }
+
+    #[test]
+    fn test_block_values() {
+        let mut interp = new_nss();
+        assert_eq!(run("{let x = 1; x + 1}", &mut interp), "- : isize = 2");
+        assert_eq!(run("{let x = 1; x + 1;}", &mut interp), "- : unit = ()");
+        assert_eq!(run("{let tmp = 0}", &mut interp), "- : unit = ()");
+        assert_eq!(run("{let tmp = 0;}", &mut interp), "- : unit = ()");
+        assert_eq!(run("{}", &mut interp), "- : unit = ()");
+        // Le `;` est facultatif après une instruction qui se termine par un bloc
+        assert_eq!(run("fn s(n: isize) -> isize { let mut a = 0; for i in 0..n { a = a + i } a }", &mut interp), "s : fn(isize) -> isize = <fn s>");
+        assert_eq!(run("s(4)", &mut interp), "- : isize = 6");
+        assert_eq!(run("{ let mut b = 0; while b < 3 { b = b + 1 } if b == 3 { b = 10 } else {} { b + 1 } }", &mut interp), "- : isize = 11");
+        assert_eq!(run("{ let c = 1; for i in 0..2 {}; c }", &mut interp), "- : isize = 1");
+        assert_eq!(run("{ for i in 0..2 {}; }", &mut interp), "- : unit = ()");
+        assert!(run("{ let c = 1 c }", &mut interp).starts_with("Parse Error"));
+        // Une affectation en fin de bloc vaut ()
+        assert_eq!(run("let mut k = 0", &mut interp), "k : isize = 0");
+        assert_eq!(run("if k == 0 { k = 5 } else { k = 6 }", &mut interp), "- : unit = ()");
+        assert_eq!(run("k", &mut interp), "- : isize = 5");
+        assert_eq!(run("{ k = 7 }", &mut interp), "- : unit = ()");
+        assert_eq!(run("k = 8", &mut interp), "- : unit = ()");
+        assert_eq!(run("let v = if true { 1; } else { 2; }", &mut interp), "v : unit = ()");
+        assert_eq!(run("if true { 1; } else { 2 }", &mut interp), "Evaluation Error: `if` and `else` have incompatible types. Expected: unit. Found: isize");
+        assert_eq!(run("fn one() -> isize { 1; }", &mut interp), "one : fn() -> isize = <fn one>");
+        assert_eq!(run("one()", &mut interp), "Evaluation Error: Type mismatch in expression `one()`. Expected: isize. Found: unit");
+    }
+
+    #[test]
+    fn test_program() {
+        let mut interp = new_nss();
+        let program = "fn fact(n: isize) -> isize {\n\tif n == 0 { 1 } else { n * fact(n - 1) }\n}\n\nlet f = fact(5);\nf + 1\n";
+        assert_eq!(parse_exec_program(program, &mut interp), Ok((None, Value::Integer(121))));
+        assert_eq!(run("f", &mut interp), "- : isize = 120");
+        assert_eq!(parse_exec_program("let a = 1;\nlet b = a / 0;\nlet c = 2;", &mut interp).map_err(|e| e.to_string()), Err("Evaluation Error: Division by zero, `0` evaluates to 0".to_string()));
+        assert_eq!(run("a", &mut interp), "- : isize = 1");
+        assert_eq!(run("c", &mut interp), "Evaluation Error: Undefined identifier `c`.");
+        assert_eq!(parse_exec_program("let d = 1;\nlet = 2;", &mut interp).map_err(|e| e.to_string()), Err("Parse Error: Cannot parse, line 2, column 5\n  |\n2 | let = 2;\n  |     ^\nExpected: identifier".to_string()));
+        assert_eq!(run("d", &mut interp), "Evaluation Error: Undefined identifier `d`.");
+        // Un saut de ligne n'est qu'un blanc : seul le `;` sépare deux instructions, sauf après un bloc
+        let count = |input: &str| {
+            let mut count = 0;
+            Interpreter::new().eval_each(input, |_, _, _| count += 1).map(|()| count).map_err(|e| e.to_string())
+        };
+        assert_eq!(count("let y = f\n(3)"), Ok(1));
+        assert_eq!(count("let y = f;\n(3)"), Ok(2));
+        assert_eq!(count("fn g() {}\nwhile false {}\nlet z = 1"), Ok(3));
+        assert_eq!(count("let a = 1 let b = 2"), Err("Parse Error: Cannot parse, line 1, column 11\n  |\n1 | let a = 1 let b = 2\n  |           ^\nExpected: end of input, operator, method call, field access, call, `;`".to_string()));
+    }
+
+    #[test]
+    fn test_multiline_input() {
+        assert!(is_complete("let x = 1\n"));
+        assert!(!is_complete("while (k < 4) {\n"));
+        assert!(!is_complete("while (k < 4) {\n\tk = k + (1\n"));
+        assert!(is_complete("while (k < 4) {\n\tk = k + (1\n)}\n"));
+        assert!(is_complete("}\n"));
+        assert!(is_complete("let x = 1 // {\n"));
+        assert!(!is_complete("let x = 1 /* {\n"));
+        assert!(!is_complete("let x = 1 /* /* */ }\n"));
+        assert!(is_complete("let x = 1 /* /* */ } */\n"));
+        assert!(is_complete("println!(\"http://x\")\n"));
+        assert!(is_complete("println!(\":(\")\n"));
+        assert!(is_complete("println!(\"{{ \\\" /* \\\\\")\n"));
+        assert!(!is_complete("println!(\"a\n"));
+        assert!(!is_complete("println!(\"\\\")\n"));
+        assert!(is_complete("let s = \"// {\"; // \"\n"));
+
+        let mut interp = new_nss();
+        assert_eq!(run("let mut k = 0", &mut interp), "k : isize = 0");
+        assert_eq!(run("while (k < 4) {\n\tk = k + 3\n}", &mut interp), "- : unit = ()");
+        assert_eq!(run("k", &mut interp), "- : isize = 6");
+    }
+
+    #[test]
+    fn test_comments() {
+        let mut interp = new_nss();
+        assert_eq!(run("1 + 1 // two", &mut interp), "- : isize = 2");
+        assert_eq!(run("1 /* one */ + /* two */ 2", &mut interp), "- : isize = 3");
+        assert_eq!(run("1 /* outer /* inner */ still outer */ + 1", &mut interp), "- : isize = 2");
+        assert_eq!(run("4 / /* not a comment end */ 2", &mut interp), "- : isize = 2");
+        let program = "//! Module documentation\n/// Doubles `n`\nfn double(n: isize) -> isize {\n    n * 2 // tail\n}\n/** Block doc */\nlet d = double(4);";
+        assert_eq!(parse_exec_program(program, &mut interp), Ok((Some("d".to_string()), Value::Unit)));
+        assert_eq!(interp.get("d"), Ok(Value::Integer(8)));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let mut interp = new_nss();
+        assert_eq!(run("1 +", &mut interp), "Parse Error: Cannot parse, line 1, column 4\n  |\n1 | 1 +\n  |    ^\nExpected: expression");
+        assert_eq!(run("let x = 1 2", &mut interp), "Parse Error: Cannot parse, line 1, column 11\n  |\n1 | let x = 1 2\n  |           ^\nExpected: end of input, operator, method call, field access, call");
+        assert_eq!(run("fn f(x isize) {}", &mut interp), "Parse Error: Cannot parse, line 1, column 6\n  |\n1 | fn f(x isize) {}\n  |      ^\nExpected: `self`, parameter");
+        let program = "let a = 1;\n\tlet b = ;";
+        assert_eq!(parse_exec_program(program, &mut interp).map_err(|e| e.to_string()), Err("Parse Error: Cannot parse, line 2, column 10\n  |\n2 | \tlet b = ;\n  | \t        ^\nExpected: expression".to_string()));
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let mut interp = new_nss();
+        let error = |input: &str, interp: &mut Interpreter| parse_exec(input, interp).map_err(|e| e.report()).unwrap_err();
+        assert_eq!(error("1 / (1 - 1)", &mut interp), "Evaluation Error: Division by zero, `(1 - 1)` evaluates to 0\n  |\n1 | 1 / (1 - 1)\n  |     ^^^^^^^");
+        assert_eq!(error("let x = 2 * (y + 1)", &mut interp), "Evaluation Error: Undefined identifier `y`.\n  |\n1 | let x = 2 * (y + 1)\n  |              ^");
+        assert_eq!(error("1 + (true)", &mut interp), "Evaluation Error: Type mismatch in expression `(true)`. Expected: isize. Found: bool\n  |\n1 | 1 + (true)\n  |     ^^^^^^");
+        assert_eq!(error("{ let a = 1; a = 2 }", &mut interp), "Evaluation Error: Value at `a` is not mutable.\n  |\n1 | { let a = 1; a = 2 }\n  |              ^^^^^");
+        // L'erreur levée dans le corps d'une fonction déclarée plus tôt pointe dans sa déclaration
+        let program = "fn half(n: isize) -> isize {\n    n / (n - n)\n}";
+        assert!(parse_exec_program(program, &mut interp).is_ok());
+        assert_eq!(error("half(4)", &mut interp), "Evaluation Error: Division by zero, `(n - n)` evaluates to 0\n  |\n2 |     n / (n - n)\n  |         ^^^^^^^");
+        assert_eq!(parse_exec_program("let b = 1;\nlet c = b + z;", &mut interp).map_err(|e| e.report()), Err("Evaluation Error: Undefined identifier `z`.\n  |\n2 | let c = b + z;\n  |             ^".to_string()));
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut interp = new_nss();
+        assert_eq!(run_command(":env", &mut interp), "[0] (empty)");
+        run("let mut y = true", &mut interp);
+        run("let x = 1", &mut interp);
+        assert_eq!(run_command(":env", &mut interp), "[0]\n  x : isize = 1\n  mut y : bool = true");
+        assert_eq!(run_command(":heap", &mut interp), "Heap is empty.");
+        assert_eq!(run_command(":type x + 1", &mut interp), "- : isize");
+        assert_eq!(run_command(":type if y { x } else { 0 }", &mut interp), "- : isize");
+        assert_eq!(run_command(":type x / 0", &mut interp), "- : isize");
+        assert_eq!(run_command(":type z", &mut interp), "Evaluation Error: Undefined identifier `z`.");
+        assert_eq!(run_command(":type", &mut interp), "Missing argument, usage: :type <expr>");
+        assert_eq!(run_command(":foo", &mut interp), "Unknown command `:foo`. Available commands: :env, :heap, :type <expr>, :reset, :load <file>, :dot [file], :save <file>, :restore <file>, :checkpoint, :rollback, :undo, :redo");
+        let path = std::env::temp_dir().join("microrust_test_commands.rs");
+        std::fs::write(&path, "let z = x + 1;\nfn twice(n: isize) -> isize { n * 2 }").unwrap();
+        assert_eq!(run_command(&format!(":load {}", path.display()), &mut interp), "twice : fn(isize) -> isize = <fn twice>");
+        assert_eq!(run("twice(z)", &mut interp), "- : isize = 4");
+        assert_eq!(run_command(":load /nonexistent/file.rs", &mut interp), "Cannot read `/nonexistent/file.rs`: No such file or directory (os error 2)");
+        assert_eq!(run_command(":reset", &mut interp), "Session reset.");
+        assert_eq!(run_command(":env", &mut interp), "[0] (empty)");
+        assert_eq!(run_command(":heap", &mut interp), "Heap is empty.");
+        run("let mut p = Ptr::new()", &mut interp);
+        run("let mut q = Ptr::new()", &mut interp);
+        run("*p = 3", &mut interp);
+        assert_eq!(run_command(":heap", &mut interp), "@[0] : isize = 3\n@[1] : not initialized");
+        run("free(p)", &mut interp);
+        assert_eq!(run_command(":heap", &mut interp), "@[0] : not allocated\n@[1] : not initialized");
+        run_command(":reset", &mut interp);
+        assert_eq!(run("x", &mut interp), "Evaluation Error: Undefined identifier `x`.");
+    }
+
+    #[test]
+    fn test_completion() {
+        let mut interp = new_nss();
+        run("let counter = 0", &mut interp);
+        run("struct Point { x: isize }", &mut interp);
+        let mut helper = editor::MicroRustHelper::new();
+        helper.set_identifiers(interp.identifiers());
+        let words = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(helper.candidates("co", 2), (0, words(&["continue", "counter"])));
+        assert_eq!(helper.candidates("let x = cou", 11), (8, words(&["counter"])));
+        assert_eq!(helper.candidates("P", 1), (0, words(&["Point", "Ptr"])));
+        assert_eq!(helper.candidates("wh", 2), (0, words(&["while"])));
+        assert_eq!(helper.candidates("1 + ", 4), (4, vec![]));
+        assert_eq!(helper.candidates(":re", 3), (1, words(&["reset", "restore", "redo"])));
+        assert_eq!(helper.candidates("\"é", 3), (3, vec![]));
+        assert_eq!(helper.candidates("\"→co", 6), (4, words(&["continue", "counter"])));
+    }
+
+    #[test]
+    fn test_json() {
+        let mut interp = new_nss();
+        let mut out = vec![];
+        let code = run_json("let x = 1;\nx / 0;\n{ let y = x; }\nstruct P { a: isize }\nx + 1", &mut interp, &mut out);
+        assert_eq!(code, ExitCode::from(1));
+        let lines: Vec<serde_json::Value> = String::from_utf8(out).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines, vec![
+            json!({"id": "x", "type": "isize", "value": "1", "output": "", "error": null, "message": null}),
+            json!({"id": null, "type": null, "value": null, "output": "", "error": "DivisionByZero", "message": "Division by zero, `0` evaluates to 0"}),
+            json!({"id": null, "type": "unit", "value": "()", "output": "", "error": null, "message": null}),
+            json!({"id": "P", "type": "unit", "value": "()", "output": "", "error": null, "message": null}),
+            json!({"id": null, "type": "isize", "value": "2", "output": "", "error": null, "message": null}),
+        ]);
+        let mut out = vec![];
+        assert_eq!(run_json("let = 2", &mut interp, &mut out), ExitCode::from(2));
+        let line: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(line["error"], "CannotParse");
+        let mut out = vec![];
+        assert_eq!(run_json("x", &mut interp, &mut out), ExitCode::SUCCESS);
+    }
+
+    #[test]
+    fn test_interpreter() {
+        let mut interp = Interpreter::new();
+        assert_eq!(interp.define("n", false, Value::Integer(20)), Ok(()));
+        assert_eq!(interp.define("flag", true, Value::Boolean(false)), Ok(()));
+        assert_eq!(interp.define("n", false, Value::Integer(1)).map_err(|e| e.to_string()), Err("Evaluation Error: Identifier `n` already defined.".to_string()));
+        assert_eq!(interp.eval_str("let m = n * 2;\nflag = m > 30;\nm + 2"), Ok((None, Value::Integer(42))));
+        assert_eq!(interp.get("m"), Ok(Value::Integer(40)));
+        assert_eq!(interp.get("flag"), Ok(Value::Boolean(true)));
+        assert_eq!(interp.get("p").map_err(|e| e.to_string()), Err("Evaluation Error: Undefined identifier `p`.".to_string()));
+        assert_eq!(interp.type_of("n == m"), Ok(Type::Bool));
+        assert_eq!(interp.eval_str("let a = 1; a + n"), Ok((None, Value::Integer(21))));
+        assert!(matches!(interp.eval_str("let = 1"), Err(Error::ParseError(_))));
+        assert_eq!(interp.eval_str("n / 0").map_err(|e| e.to_string()), Err("Evaluation Error: Division by zero, `0` evaluates to 0".to_string()));
+        // Deux interpréteurs ne partagent rien
+        assert!(Interpreter::new().get("n").is_err());
+    }
+
+    #[test]
+    fn test_native_functions() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        let mut interp = Interpreter::new();
+        let printed = Rc::new(RefCell::new(vec![]));
+        let out = printed.clone();
+        interp.register("print", vec![Type::Infer], Type::Unit, move |args| {
+            out.borrow_mut().push(args[0].to_string());
+            Ok(Value::Unit)
+        }).unwrap();
+        let inputs = RefCell::new(vec![Value::Integer(7), Value::Integer(5)]);
+        interp.register("read_int", vec![], Type::Int, move |_| inputs.borrow_mut().pop().ok_or("no more input".to_string())).unwrap();
+        interp.register("max", vec![Type::Int, Type::Int], Type::Int, |args| match args {
+            [Value::Integer(a), Value::Integer(b)] => Ok(Value::Integer(*a.max(b))),
+            _ => unreachable!("arguments are checked before the call"),
+        }).unwrap();
+        interp.register("broken", vec![], Type::Int, |_| Ok(Value::Boolean(true))).unwrap();
+        assert_eq!(run("max", &mut interp), "- : fn(isize, isize) -> isize = <native fn max>");
+        assert_eq!(run("max(read_int(), read_int() + 1)", &mut interp), "- : isize = 8");
+        assert_eq!(run("{ print(1 + 1); print(true) }", &mut interp), "- : unit = ()");
+        assert_eq!(*printed.borrow(), vec!["2", "true"]);
+        assert_eq!(run("read_int()", &mut interp), "Evaluation Error: Function `read_int` failed: no more input");
+        assert_eq!(run("max(1, true)", &mut interp), "Evaluation Error: Type mismatch in expression `true`. Expected: isize. Found: bool");
+        assert_eq!(run("max(1)", &mut interp), "Evaluation Error: Function `max` takes 2 argument(s) but 1 were supplied.");
+        assert_eq!(run("broken()", &mut interp), "Evaluation Error: Type mismatch in expression `broken()`. Expected: isize. Found: bool");
+        assert_eq!(interp.type_of("max(1, 2) + 1"), Ok(Type::Int));
+        assert_eq!(parse_exec_program("let f = |x| max(x, 10); f(3)", &mut interp), Ok((None, Value::Integer(10))));
+    }
+
+    #[test]
+    fn test_macros() {
+        let mut interp = new_nss();
+        let text = |input: &str, interp: &mut Interpreter| match parse_exec_program(input, interp) {
+            Ok((_, Value::Str(s))) => s.to_string(),
+            res => panic!("{:?}", res),
+        };
+        assert_eq!(run("let n = 42", &mut interp), "n : isize = 42");
+        assert_eq!(run("format!(\"n = {}, {}\", n, true)", &mut interp), "- : String = n = 42, true");
+        assert_eq!(text("format!(\"{1}{0}{1} {x} {n}\", 1, 2, x = n + 1)", &mut interp), "212 43 42");
+        assert_eq!(text("format!(\"[{:>5}] [{:<4}] [{:^6}] [{:*^5}] [{:04}] [{:.3}]\", 12, 3, \"ab\", 1, 0 - 7, \"hello\")", &mut interp),
+            "[   12] [3   ] [  ab  ] [**1**] [-007] [hel]");
+        assert_eq!(text("struct P { s: String }; format!(\"{:?} {:?} {}\", P { s: \"a\\\"b\" }, \"x\", P { s: \"c\" })", &mut interp),
+            "P { s: \"a\\\"b\" } \"x\" P { s: c }");
+        assert_eq!(text("format!(\"{{}}\\t{}\", 1)", &mut interp), "{}\t1");
+        assert_eq!(parse_exec_program("format!(\"a\") == \"a\"", &mut interp), Ok((None, Value::Boolean(true))));
+        assert_eq!(interp.type_of("format!(\"{}\", 1)"), Ok(Type::Str));
+        // Les macros d'affichage valent ()
+        let (res, printed) = interp.capture(|interp| parse_exec_program("print!(\"{}-\", n); println!(\"{n:?}\"); println!()", interp));
+        assert_eq!(res, Ok((None, Value::Unit)));
+        assert_eq!(printed, "42-42\n\n");
+        assert_eq!(run("println!(\"{}\")", &mut interp), "Evaluation Error: Invalid format: invalid reference to positional argument 0 (0 argument(s) given).");
+        assert_eq!(run("println!(\"{}\", 1, 2)", &mut interp), "Evaluation Error: Invalid format: argument `2` is never used.");
+        // La précision ne tronque pas un nombre
+        assert_eq!(text("format!(\"[{:.1}] [{:6.2}] [{:.2}]\", 123, 0 - 45, \"abc\")", &mut interp), "[123] [   -45] [ab]");
+        assert_eq!(run("println!(\"{99999999999999999999999}\", 1)", &mut interp), "Evaluation Error: Invalid format: invalid reference to positional argument 99999999999999999999999.");
+        assert_eq!(run("println!(\"{:x}\", 1)", &mut interp), "Evaluation Error: Invalid format: unknown format specifier `x` in `{:x}`.");
+        assert_eq!(run("println!(n)", &mut interp), "Evaluation Error: Invalid format: println! expects a string literal as its first argument.");
+        assert_eq!(run("println!(\"{y}\")", &mut interp), "Evaluation Error: Undefined identifier `y`.");
+        // Une closure capture les variables nommées dans la chaîne de format
+        assert_eq!(text("let mut k = 5; let f = move || format!(\"{k}\"); k = 6; f()", &mut interp), "5");
+        assert!(matches!(parse_exec("foo!(1)", &mut interp), Err(Error::ParseError(_))));
+    }
+
+    #[test]
+    fn test_panics() {
+        let mut interp = new_nss();
+        assert_eq!(run("assert!(1 < 2)", &mut interp), "- : unit = ()");
+        assert_eq!(run("assert_eq!(format!(\"{}\", 12), \"12\", \"never shown\")", &mut interp), "- : unit = ()");
+        assert_eq!(run("assert_ne!(1, 2)", &mut interp), "- : unit = ()");
+        assert_eq!(run("assert!(1 > 2)", &mut interp), "Evaluation Error: Panicked: assertion failed: 1 > 2");
+        assert_eq!(run("assert!(false, \"x = {}\", 3)", &mut interp), "Evaluation Error: Panicked: x = 3");
+        assert_eq!(run("assert_eq!(1 + 1, 3)", &mut interp), "Evaluation Error: Panicked: assertion `left == right` failed\n  left: 2\n right: 3");
+        assert_eq!(run("assert_ne!(\"a\", \"a\", \"{}\", 1)", &mut interp), "Evaluation Error: Panicked: assertion `left != right` failed: 1\n  left: \"a\"\n right: \"a\"");
+        assert_eq!(run("panic!()", &mut interp), "Evaluation Error: Panicked: explicit panic");
+        assert_eq!(run("assert_eq!(1, false)", &mut interp), "Evaluation Error: Type mismatch in expression `false`. Expected: isize. Found: bool");
+        assert_eq!(run("assert!()", &mut interp), "Evaluation Error: Function `assert!` takes 1 argument(s) but 0 were supplied.");
+        // La panique remonte tous les NameSpace jusqu'au global et porte la portion de la macro
+        let res = parse_exec_program("fn f(n: isize) { let k = n; { for i in 0..n { if i == 2 { panic!(\"i = {i}\") } } } }\nf(5)", &mut interp);
+        assert_eq!(res.as_ref().map_err(Error::report), Err("Evaluation Error: Panicked: i = 2\n  |\n1 | fn f(n: isize) { let k = n; { for i in 0..n { if i == 2 { panic!(\"i = {i}\") } } } }\n  |                                                           ^^^^^^^^^^^^^^^^^".to_string()));
+        assert_eq!(exit_code(&res.unwrap_err()), ExitCode::from(101));
+        assert!(!interp.display_env().contains("[1]"));
+        assert_eq!(run("k", &mut interp), "Evaluation Error: Undefined identifier `k`.");
+        let mut out = vec![];
+        assert_eq!(run_json("assert!(true); panic!(\"no\"); 1 / 0", &mut interp, &mut out), ExitCode::from(101));
+    }
+
+    #[test]
+    fn test_test_runner() {
+        let input = "fn double(n: isize) -> isize { n * 2 }\n\
+            #[test]\nfn doubles() { assert_eq!(double(2), 4) }\n\
+            #[test] fn wrong() { println!(\"checking\"); assert!(double(1) == 3) }\n\
+            #[test] fn undefined() { let y = 1; z }\n\
+            #[test] fn isolated() { let y = 2; assert_eq!(y, 2) }";
+        let mut out = vec![];
+        assert_eq!(run_tests(input, &mut out), ExitCode::from(101));
+        let out = String::from_utf8(out).unwrap();
+        // Les durées varient d'une exécution à l'autre
+        let lines: Vec<&str> = out.lines().map(|line| line.split(" (").next().unwrap()).collect();
+        assert_eq!(&lines[..6], ["", "running 4 tests", "test doubles ... ok", "test wrong ... panicked", "test undefined ... FAILED", "test isolated ... ok"]);
+        assert!(out.contains("---- wrong ----\nchecking\nEvaluation Error: Panicked: assertion failed: double(1) == 3\n"));
+        assert!(out.contains("---- undefined ----\nEvaluation Error: Undefined identifier `z`."));
+        assert!(out.contains("failures:\n    wrong\n    undefined\n"));
+        assert!(out.contains("test result: FAILED. 2 passed; 1 failed; 1 panicked; finished in "));
+        let mut out = vec![];
+        assert_eq!(run_tests("#[test] fn t() { }\nfn helper() { }", &mut out), ExitCode::SUCCESS);
+        assert!(String::from_utf8(out).unwrap().contains("running 1 test\ntest t ... ok"));
+        let mut out = vec![];
+        assert_eq!(run_tests("#[test] fn", &mut out), ExitCode::from(2));
+        // Dans la boucle interactive, l'attribut est accepté et la fonction simplement déclarée
+        assert!(matches!(parse_exec("#[test] fn f() { }", &mut new_nss()), Ok((Some(_), Value::Function(fun))) if fun.is_test()));
+    }
+
+    #[test]
+    fn test_test_runner_heap() {
+        let input = "#[test] fn leaks() { let mut p = Ptr::new(); *p = 1 }\n\
+            #[test] fn clean() { let mut p = Ptr::new(); *p = 1; assert_eq!(*p, 1); free(p) }\n\
+            #[test] fn panics() { let mut p = Ptr::new(); panic!(\"stop\") }\n\
+            #[test] fn overflows() { 9223372036854775807 + 1 }\n\
+            #[test] fn last() { }";
+        let mut out = vec![];
+        assert_eq!(run_tests(input, &mut out), ExitCode::from(101));
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().map(|line| line.split(" (").next().unwrap()).collect();
+        // Un débordement arrête l'interpréteur compilé avec ses vérifications, pas les tests suivants
+        let overflows = if cfg!(debug_assertions) { "test overflows ... FAILED" } else { "test overflows ... ok" };
+        assert_eq!(&lines[..7], ["", "running 5 tests", "test leaks ... FAILED", "test clean ... ok", "test panics ... panicked", overflows, "test last ... ok"]);
+        assert!(out.contains("---- leaks ----\nEvaluation Error: leaking @[0]\n"));
+        assert!(out.contains("---- panics ----\nEvaluation Error: Panicked: stop\n"));
+        if cfg!(debug_assertions) {
+            assert!(out.contains("---- overflows ----\nInternal error: attempt to add with overflow\n"));
+        }
+        assert!(out.contains("test result: FAILED. "));
+    }
+
+    // Chaque `tests/golden/*.rs` est rejoué dans la boucle interactive et comparé au `.out` du même nom.
+    // `BLESS=1 cargo test test_golden` réécrit les `.out` d'après la sortie obtenue.
+    #[test]
+    fn test_golden() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+        let bless = std::env::var_os("BLESS").is_some();
+        let mut paths: Vec<_> = std::fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty(), "no golden file in {}", dir.display());
+        let mut failures = vec![];
+        for path in paths {
+            let actual = transcript(&std::fs::read_to_string(&path).unwrap());
+            let expected_path = path.with_extension("out");
+            if bless {
+                std::fs::write(&expected_path, &actual).unwrap();
+                continue;
+            }
+            match std::fs::read_to_string(&expected_path) {
+                Ok(expected) if expected == actual => (),
+                Ok(expected) => failures.push(format!("{}\n{}", expected_path.display(), diff(&expected, &actual))),
+                Err(_) => failures.push(format!("{} is missing, run `BLESS=1 cargo test test_golden`", expected_path.display())),
             }
         }
+        assert!(failures.is_empty(), "golden transcripts differ:\n{}", failures.join("\n"));
+    }
+
+    // Sortie du débogueur, encore lisible une fois le débogueur installé
+    #[derive(Clone, Default)]
+    struct SharedBuf(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+    impl io::Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_debugger() {
+        let program = "fn inner(n: isize) -> isize {\n    let m = n * 2;\n    m + 1\n}\n\
+            fn outer(n: isize) -> isize {\n    let r = inner(n);\n    r\n}\n\
+            let x = 1;\nlet p = &x;\nlet y = outer(x);\ny + 1";
+        let commands = "s\nbreak 3\nb 12\ncontinue\nbacktrace\nprint m + n\nprint *p\nprint q\nnext\nnext\nbt\nprint y\nbreak\njump\nc\n";
+        let out = SharedBuf::default();
+        let mut interp = new_nss();
+        interp.attach_debugger(io::Cursor::new(commands), out.clone());
+        let res = parse_exec_program(program, &mut interp);
+        assert!(interp.detach_debugger());
+        assert_eq!(res, Ok((None, Value::Integer(4))));
+        let out = String::from_utf8(out.0.borrow().clone()).unwrap();
+        let stops: Vec<&str> = out.lines().filter(|line| line.contains("Stopped before")).collect();
+        assert_eq!(stops, ["Stopped before line 1", "(debug) Stopped before line 5", "(debug) Stopped before line 3",
+            "(debug) Stopped before line 7", "(debug) Stopped before line 12"]);
+        assert!(out.contains("#0 inner at line 3, 2 namespace(s)\n#1 outer at line 6, 2 namespace(s)\n#2 main at line 11, 1 namespace(s)\nNameSpaceStack depth: 5\n"));
+        assert!(out.contains("(debug) m + n : isize = 3\n(debug) *p : isize = 1\n(debug) Evaluation Error: Undefined identifier `q`."));
+        assert!(out.contains("(debug) #0 main at line 12, 1 namespace(s)\nNameSpaceStack depth: 1\n"));
+        assert!(out.contains("(debug) y : isize = 3\n(debug) Usage: break <line>\n(debug) Unknown command `jump`."));
+        // Sans débogueur, aucun arrêt
+        assert_eq!(parse_exec_program(program, &mut new_nss()), Ok((None, Value::Integer(4))));
+    }
+
+    #[test]
+    fn test_trace() {
+        let program = "fn add(a: isize, b: isize) -> isize { a + b }\nlet mut x = 1;\nx = add(x, 2);\nfalse && (1 / 0 == 1)";
+        let out = SharedBuf::default();
+        let mut interp = new_nss();
+        interp.start_trace(out.clone());
+        let res = parse_exec_program(program, &mut interp);
+        interp.stop_trace();
+        // `&&` n'évalue pas son second opérande quand le premier est faux
+        assert_eq!(res, Ok((None, Value::Boolean(false))));
+        let out = String::from_utf8(out.0.borrow().clone()).unwrap();
+        assert!(out.contains("let mut x = 1 => ()\n  @[0, x] := 1\n"));
+        assert!(out.contains("2 => 2\n  call add\n    @[1, a] := 1\n    @[1, b] := 2\n    a => 1\n    b => 2\n    a + b => 3\nadd(x, 2) => 3\n"));
+        assert!(out.contains("x = add(x, 2) => ()\n  @[0, x] := 3\n"));
+        assert!(out.ends_with("false => false\nfalse && (1 / 0 == 1) => false\n"));
+        assert!(!out.contains("1 / 0 =>"));
+        assert_eq!(run("true && 1 / 0 == 1", &mut new_nss()), "Evaluation Error: Division by zero, `0` evaluates to 0");
+    }
+
+    #[test]
+    fn test_dot() {
+        let mut interp = new_nss();
+        run("let mut x = 1", &mut interp);
+        run("let p = &x", &mut interp);
+        let graph = run_command(":dot", &mut interp);
+        assert!(graph.starts_with("digraph memory {"));
+        assert!(graph.contains("label=\"main\";"));
+        assert!(graph.contains("<td port=\"x\" align=\"left\">mut x : isize = 1</td>"));
+        assert!(graph.contains("  ns0:p -> ns0:x;\n}"));
+        assert!(run_command(":dot /nonexistent/memory.dot", &mut interp).starts_with("Cannot write `/nonexistent/memory.dot`"));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let path = std::env::temp_dir().join(format!("microrust-state-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut interp = new_nss();
+        for line in ["struct P { a: isize }", "impl P { fn get(&self) -> isize { self.a } }",
+            "fn double(n: isize) -> isize { n * 2 }", "let g = double", "let mut x = P { a: 3 }",
+            "let p = &x", "let k = 10", "let add = |n: isize| n + k", "let s = \"hi\""] {
+            run(line, &mut interp);
+        }
+        assert_eq!(run_command(&format!(":save {}", path), &mut interp), format!("State saved to `{}`.", path));
+        let env = run_command(":env", &mut interp);
+        run_command(":reset", &mut interp);
+        assert_eq!(run_command(&format!(":restore {}", path), &mut interp), format!("State restored from `{}`.", path));
+        assert_eq!(run_command(":env", &mut interp), env);
+        assert_eq!(run("p.get()", &mut interp), "- : isize = 3");
+        assert_eq!(run("g(4) + add(1)", &mut interp), "- : isize = 19");
+        assert_eq!(run("x.a = 5", &mut interp), "- : unit = ()");
+        assert_eq!(run("(*p).get()", &mut interp), "- : isize = 5");
+        assert_eq!(run("let s = 1", &mut interp), "Evaluation Error: Identifier `s` already defined.");
+
+        // Points de reprise en mémoire
+        assert_eq!(run_command(":rollback", &mut interp), "No checkpoint to roll back to.");
+        assert_eq!(run_command(":checkpoint", &mut interp), "Checkpoint saved.");
+        run("x.a = 7", &mut interp);
+        run("let y = 1", &mut interp);
+        assert_eq!(run_command(":rollback", &mut interp), "Rolled back to the checkpoint.");
+        assert_eq!(run("x.a", &mut interp), "- : isize = 5");
+        assert_eq!(run("y", &mut interp), "Evaluation Error: Undefined identifier `y`.");
+
+        // Fichiers invalides : la session n'est pas modifiée
+        std::fs::write(path, "{\"format\": \"microrust-state\", \"version\": 2}").unwrap();
+        assert_eq!(run_command(&format!(":restore {}", path), &mut interp),
+            format!("Cannot restore `{}`: unsupported version 2 (expected 1).", path));
+        std::fs::write(path, "[]").unwrap();
+        assert_eq!(run_command(&format!(":restore {}", path), &mut interp),
+            format!("Cannot restore `{}`: not a µRust state file.", path));
+        assert_eq!(run("x.a", &mut interp), "- : isize = 5");
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(run_command(":save", &mut interp), "Missing argument, usage: :save <file>");
+        interp.register("id", vec![Type::Int], Type::Int, |args| Ok(args[0].clone())).unwrap();
+        assert_eq!(run_command(&format!(":save {}", path), &mut interp),
+            "Cannot save the state: native function `id` cannot be saved.");
+
+        // Une cellule allouée mais pas encore écrite le reste après une reprise
+        let mut interp = new_nss();
+        run("let mut u = Ptr::new()", &mut interp);
+        assert_eq!(run_command(&format!(":save {}", path), &mut interp), format!("State saved to `{}`.", path));
+        run_command(":reset", &mut interp);
+        assert_eq!(run_command(&format!(":restore {}", path), &mut interp), format!("State restored from `{}`.", path));
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(run_command(":heap", &mut interp), "@[0] : not initialized");
+        run("*u = 4", &mut interp);
+        assert_eq!(run("*u", &mut interp), "- : isize = 4");
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut interp = new_nss();
+        let mut entry = |input: &str| if input.starts_with(':') {
+            run_command(input, &mut interp)
+        } else {
+            eval_entry(input, &mut interp).unwrap_or_default()
+        };
+        assert_eq!(entry(":undo"), "Nothing to undo.");
+        assert_eq!(entry("let mut x = 1"), "x : isize = 1");
+        assert_eq!(entry("x = 10 * y"), "Evaluation Error: Undefined identifier `y`.\n  |\n1 | x = 10 * y\n  |          ^");
+        assert_eq!(entry("x = 2"), "- : unit = ()");
+        // Les instructions qui ne modifient rien ne comptent pas
+        assert_eq!(entry("x + 1"), "- : isize = 3");
+        assert_eq!(entry(":undo"), "Undone.");
+        assert_eq!(entry("x"), "- : isize = 1");
+        assert_eq!(entry(":undo"), "Undone.");
+        assert_eq!(entry("x"), "Evaluation Error: Undefined identifier `x`.\n  |\n1 | x\n  | ^");
+        assert_eq!(entry(":undo"), "Nothing to undo.");
+        assert_eq!(entry(":redo"), "Redone.");
+        assert_eq!(entry(":redo"), "Redone.");
+        assert_eq!(entry(":redo"), "Nothing to redo.");
+        assert_eq!(entry("x"), "- : isize = 2");
+        // Une nouvelle modification efface ce qui pouvait être refait
+        assert_eq!(entry(":undo"), "Undone.");
+        assert_eq!(entry("let y = 3"), "y : isize = 3");
+        assert_eq!(entry(":redo"), "Nothing to redo.");
+        // Un retour au point de reprise s'annule aussi
+        assert_eq!(entry(":checkpoint"), "Checkpoint saved.");
+        assert_eq!(entry("x = 5"), "- : unit = ()");
+        assert_eq!(entry(":rollback"), "Rolled back to the checkpoint.");
+        assert_eq!(entry(":undo"), "Undone.");
+        assert_eq!(entry("x"), "- : isize = 5");
+    }
+
+    // La machine à pile doit donner les mêmes valeurs, sorties et erreurs que l'évaluateur
+    #[test]
+    fn test_vm() {
+        let programs = [
+            "fn fib(n: isize) -> isize { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }\nlet mut total = 0;\nfor i in 0..9 { if i % 3 == 0 { continue }; if i > 6 { break }; total = total + fib(i) }\ntotal",
+            "let mut n = 10;\nwhile n > 0 { n = n - 3 }\nprintln!(\"n = {n}, {}\", n * 2);\nlet s = format!(\"{0}-{0}\", 7);\nassert_eq!(s, \"7-7\");\n{ let x = 1; { let x = 2; print!(\"{x}\") }; x }",
+            "fn add(a: isize, b: isize) -> isize { let c = a + b; c }\nlet f = add;\nlet t = true || 1 / 0 == 0;\nif t { f(2, 3) } else { 0 }",
+            "let x = 5;\nfn g(a: isize) -> isize { a / (x - 5) }\nprintln!(\"before\");\ng(3)",
+            "let a = 1;\na = 2",
+            "fn f(b: bool) -> isize { if b { 1 } else { 2 } }\nf(3)",
+            "fn h() -> isize { true }\n1 + h()",
+            "let v = 3;\nassert_eq!(v + 1, 5, \"v was {v}\")",
+            "assert!(1 > 2)",
+            "if true { 1 } else { false }",
+            "println!(\"start\");\nfn g(b: bool) { if b { 1 } }\ng(true)",
+            "let n = 2;\nif n == 1 { 1 } else if n == 2 { 2 }",
+            "let x = 1;\nlet x = 2;",
+            "for i in 3 { }",
+            "y + 1",
+            "panic!(\"{} {}\", 1)",
+        ];
+        for program in programs {
+            let expected = new_nss().capture(|interp| interp.eval_str(program).map(|(_, v)| v));
+            let actual = new_nss().capture(|interp| interp.run_vm(program));
+            assert_eq!(actual, expected, "{}", program);
+        }
+        // Les pointeurs ne sont pas encore traduits
+        assert_eq!(new_nss().run_vm("let x = 1;\nlet p = &x;").map_err(|e| e.to_string()), Err("Compile Error: The bytecode VM does not support pointers yet.".to_string()));
+        // Le mode `--vm` s'arrête alors sur l'erreur, sans rien exécuter
+        let mut interp = Interpreter::new();
+        let (res, out) = interp.capture(|interp| parse_run_vm("println!(\"start\");\nlet x = 1;\nlet p = &x;", interp));
+        let e = res.unwrap_err();
+        assert_eq!(e.report(), "Compile Error: The bytecode VM does not support pointers yet.");
+        assert_eq!(exit_code(&e), ExitCode::from(2));
+        assert_eq!(out, "");
+        assert!(interp.get("x").is_err());
     }
 }
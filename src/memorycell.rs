@@ -1,5 +1,7 @@
 use crate::error::EvalError;
 use crate::parsing::value::Value;
+use crate::r#type::Type;
+use std::fmt::{self, Display};
 
 #[derive(Debug, Clone)]
 pub enum MemoryCell {
@@ -24,6 +26,11 @@ impl MemoryCell {
         })
     }
 
+    // Cellule allouée dont la valeur sera donnée par la première écriture
+    pub fn uninitialized(mutable: bool) -> Self {
+        MemoryCell::AllocatedCell(AllocatedCell { mutable, value: None })
+    }
+
     pub fn is_mutable(&self) -> bool {
         match self {
             MemoryCell::NotAllocated => false,
@@ -63,15 +70,14 @@ impl MemoryCell {
     }
 }
 
-impl AllocatedCell {
-    pub fn new(mutable: bool, value: Value) -> Self {
-        AllocatedCell {
-            mutable,
-            value: Some(value),
+impl Display for MemoryCell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryCell::NotAllocated => write!(f, "not allocated"),
+            MemoryCell::AllocatedCell(cell) => match &cell.value {
+                Some(value) => write!(f, "{} = {}", Type::from(value), value),
+                None => write!(f, "not initialized"),
+            },
         }
     }
-
-    pub fn get_value(&self) -> Option<&Value> {
-        self.value.as_ref()
-    }
-}
\ No newline at end of file
+}
@@ -1,47 +1,95 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use crate::error::EvalError;
 use crate::identifier::Identifier;
 use crate::memorycell::MemoryCell;
 use crate::parsing::expression::Expression;
 use crate::parsing::value::Value;
 
+// Chaque NameSpace reçoit un identifiant unique, pour reconnaître une référence
+// vers un bloc qui a été dépilé puis remplacé par un autre au même indice
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
 #[derive(Debug, Clone)]
-pub struct NameSpace(HashMap<Identifier, MemoryCell>);
+pub struct NameSpace {
+    id: usize,
+    cells: HashMap<Identifier, MemoryCell>,
+}
+
 
 
+impl Default for NameSpace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl NameSpace {
     pub fn new() -> Self {
-        NameSpace(HashMap::new())
+        NameSpace { id: NEXT_ID.fetch_add(1, Ordering::Relaxed), cells: HashMap::new() }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
     }
 
     pub fn declare(&mut self, id: &Identifier, mutable: bool, value: Value) -> Result<(), EvalError> {
-//        self.0.try_insert(id, value).map_err(|_| EvalError::AlreadyDefined(id))
-        if self.0.contains_key(&id) {
+//        self.cells.try_insert(id, value).map_err(|_| EvalError::AlreadyDefined(id))
+        if self.cells.contains_key(id) {
             Err(EvalError::AlreadyDefined(id.clone()))
         } else {
-            self.0.insert(id.clone(), MemoryCell::new(mutable, value));
+            self.cells.insert(id.clone(), MemoryCell::new(mutable, value));
             Ok(())
         }
     }
 
+    pub fn identifiers(&self) -> impl Iterator<Item = &Identifier> {
+        self.cells.keys()
+    }
+
+    // Cellules triées par identifiant
+    pub fn cells(&self) -> Vec<(&Identifier, &MemoryCell)> {
+        let mut cells: Vec<_> = self.cells.iter().collect();
+        cells.sort_by_key(|(id, _)| id.to_string());
+        cells
+    }
+
+    pub fn contains(&self, id: &Identifier) -> bool {
+        self.cells.contains_key(id)
+    }
+
     pub fn find(&self, id: &Identifier) -> Result<Value, EvalError> {
-        match self.0.get(id) {
+        match self.cells.get(id) {
             // get_value
-            Some(get_value) => get_value.get_value().map(|v| v.clone()),
+            Some(get_value) => get_value.get_value().cloned(),
+            None => Err(EvalError::Undefined(id.clone())),
+        }
+    }
+
+    pub fn is_mutable(&self, id: &Identifier) -> Result<bool, EvalError> {
+        match self.cells.get(id) {
+            Some(memory_cell) => Ok(memory_cell.is_mutable()),
             None => Err(EvalError::Undefined(id.clone())),
         }
     }
 
-    pub fn set(&mut self, id: &Identifier, value: Value) -> Result<(), EvalError> {
-        match self.0.get_mut(id) {
+    // Écriture dans un champ (éventuellement imbriqué) de la valeur liée à `id`
+    pub fn set_field(&mut self, id: &Identifier, path: &[Identifier], value: Value) -> Result<(), EvalError> {
+        match self.cells.get_mut(id) {
             //appelle la fonction is_mutable et get_value
-            Some(memoryCell) => {
-                let mutable = memoryCell.is_mutable();
+            Some(memory_cell) => {
+                let mutable = memory_cell.is_mutable();
                 if !mutable {
                     return Err(EvalError::NotMutable(Some(Expression::Identifier(id.clone()))));
                 }
-                let _ = memoryCell.set_value(value);
+                let new_value = if path.is_empty() {
+                    value
+                } else {
+                    let mut new_value = memory_cell.get_value()?.clone();
+                    new_value.set_field(path, value)?;
+                    new_value
+                };
+                let _ = memory_cell.set_value(new_value);
                 Ok(())
 
             },
@@ -49,3 +97,13 @@ impl NameSpace {
         }
     }
 }
+
+// Une ligne par cellule, triées par identifiant : `mut x : isize = 1`
+impl std::fmt::Display for NameSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (id, cell) in self.cells() {
+            writeln!(f, "{}{} : {}", if cell.is_mutable() { "mut " } else { "" }, id, cell)?;
+        }
+        Ok(())
+    }
+}
@@ -1,59 +1,274 @@
+use std::collections::HashMap;
+use std::rc::Rc;
 use crate::error::EvalError;
+use crate::heap::Heap;
 use crate::identifier::Identifier;
 use crate::memory::Address;
 use crate::namespace::NameSpace;
+use crate::parsing::function::Function;
+use crate::parsing::structure::Structure;
 use crate::parsing::value::Value;
+use crate::trace;
 
+// Appel de fonction en cours
 #[derive(Debug, Clone)]
-pub struct NameSpaceStack { stack: Vec<NameSpace> }
+pub struct Frame {
+    pub name: Identifier,
+    // Indice du premier NameSpace de l'appel
+    pub base: usize,
+    // Variables capturées par référence par une closure : adresse et NameSpace visé
+    captures: Vec<(Identifier, Address, usize)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NameSpaceStack {
+    stack: Vec<NameSpace>,
+    frames: Vec<Frame>,
+    structs: HashMap<Identifier, Rc<Structure>>,
+    methods: HashMap<Identifier, HashMap<Identifier, Rc<Function>>>,
+    // Cellules allouées par `Ptr::new()`, désignées par les `Address::HeapAddress`
+    heap: Heap,
+    // Nombre de déclarations et d'écritures, pour savoir si une instruction a modifié l'état
+    changes: u64,
+}
 
 impl NameSpaceStack {
     pub fn new() -> Self {
-        NameSpaceStack { stack: vec![] }
+        NameSpaceStack { stack: vec![], frames: vec![], structs: HashMap::new(), methods: HashMap::new(), heap: Heap::new(), changes: 0 }
     }
 
     pub(crate) fn push(&mut self, ns: NameSpace) {
         self.stack.push(ns);
+        self.trace_namespace();
     }
 
     pub(crate) fn pop(&mut self) -> Option<NameSpace> {
         self.stack.pop()
     }
 
-    pub fn find(&self, id: &Identifier) -> Result<Value, EvalError> {
-        for ns in self.stack.iter().rev() {
-            if let Ok(v) = ns.find(id) {
-                return Ok(v);
-            }
+    pub fn namespaces(&self) -> &[NameSpace] {
+        &self.stack
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn changes(&self) -> u64 {
+        self.changes
+    }
+
+    pub fn heap(&self) -> &Heap {
+        &self.heap
+    }
+
+    pub(crate) fn set_heap(&mut self, heap: Heap) {
+        self.heap = heap;
+    }
+
+    pub(crate) fn malloc(&mut self) -> Address {
+        self.heap.alloc()
+    }
+
+    pub(crate) fn free(&mut self, addr: &Address) -> Result<(), EvalError> {
+        self.heap.free(addr)
+    }
+
+    // Variables, fonctions et structures déclarées
+    pub fn identifiers(&self) -> Vec<Identifier> {
+        self.stack.iter()
+            .flat_map(|ns| ns.identifiers())
+            .chain(self.structs.keys())
+            .cloned()
+            .collect()
+    }
+
+    // Un appel de fonction ne voit que ses propres NameSpace, ses captures et les déclarations globales
+    pub(crate) fn push_frame(&mut self, name: Identifier, ns: NameSpace, captures: Vec<(Identifier, Address, usize)>) {
+        self.frames.push(Frame { name, base: self.stack.len(), captures });
+        self.stack.push(ns);
+        self.trace_namespace();
+        if trace::enabled() {
+            trace::call(&self.frames.last().unwrap().name, self.stack.len() - 1);
         }
-        Err(EvalError::Undefined(id.clone()))
     }
 
-    pub fn declare(&mut self, id: &Identifier, mutable: bool, value: Value) -> Result<(), EvalError> {
-        self.stack.last_mut().unwrap().declare(id, mutable, value)
+    // Pour `--trace` : les cellules d'un NameSpace empilé, comme les paramètres d'un appel
+    fn trace_namespace(&self) {
+        if !trace::enabled() {
+            return;
+        }
+        let index = self.stack.len() - 1;
+        let mut ids: Vec<&Identifier> = self.stack[index].identifiers().collect();
+        ids.sort_by_key(|id| id.to_string());
+        for id in ids {
+            self.trace_write(&Address::StackAddress(index, id.clone()));
+        }
     }
 
-    pub fn set(&mut self, id: &Identifier, value: Value) -> Result<(), EvalError> {
-        for ns in self.stack.iter_mut().rev() {
-            let res = ns.set(id, value.clone());
-            match res {
-                Ok(_) => return Ok(()),
-                Err(EvalError::Undefined(_)) => (),
-                Err(EvalError::NotMutable(_)) => return res,
-                Err(EvalError::TypeMismatch{..}) => return res,
-                _ => unreachable!(),
+    fn trace_write(&self, addr: &Address) {
+        if trace::enabled() {
+            if let Ok(value) = self.read(addr) {
+                trace::write(addr, &value);
             }
         }
-        Err(EvalError::Undefined(id.clone()))
     }
 
-    pub fn get_address(&self, id: &Identifier) -> Result<Address, EvalError> {
-        for index in  (0..self.stack.len()).rev() {
-            if self.stack[index].find(id).is_ok() {
+    pub(crate) fn pop_frame(&mut self) {
+        if let Some(frame) = self.frames.pop() {
+            self.stack.truncate(frame.base);
+        }
+    }
+
+    // Adresse de la cellule liée à `id` depuis le sommet de la pile
+    fn lookup(&self, id: &Identifier) -> Result<Address, EvalError> {
+        let base = self.frames.last().map(|frame| frame.base).unwrap_or(0);
+        for index in (base..self.stack.len()).rev() {
+            if self.stack[index].contains(id) {
                 return Ok(Address::StackAddress(index, id.clone()));
             }
         }
+        if let Some(frame) = self.frames.last() {
+            if let Some((_, addr, ns_id)) = frame.captures.iter().find(|(captured, _, _)| captured == id) {
+                return self.check_live(addr, *ns_id).map(|_| addr.clone());
+            }
+        }
+        if base > 0 && self.stack[0].contains(id) {
+            return Ok(Address::StackAddress(0, id.clone()));
+        }
         Err(EvalError::Undefined(id.clone()))
+    }
 
+    // Identifiant du NameSpace qui contient la cellule à l'adresse `addr`
+    pub fn namespace_id(&self, addr: &Address) -> Option<usize> {
+        match addr {
+            Address::StackAddress(index, _) => self.stack.get(*index).map(|ns| ns.id()),
+            Address::HeapAddress(_) => None,
+        }
+    }
+
+    // Vérifie que le NameSpace visé par une référence n'a pas été dépilé
+    fn check_live(&self, addr: &Address, ns_id: usize) -> Result<(), EvalError> {
+        match addr {
+            Address::StackAddress(_, id) if self.namespace_id(addr) != Some(ns_id) => {
+                Err(EvalError::DanglingReference(id.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn find(&self, id: &Identifier) -> Result<Value, EvalError> {
+        let addr = self.lookup(id)?;
+        self.read(&addr)
+    }
+
+    pub fn declare(&mut self, id: &Identifier, mutable: bool, value: Value) -> Result<(), EvalError> {
+        self.stack.last_mut().unwrap().declare(id, mutable, value)?;
+        self.changes += 1;
+        self.trace_write(&Address::StackAddress(self.stack.len() - 1, id.clone()));
+        Ok(())
+    }
+
+    pub fn set(&mut self, id: &Identifier, value: Value) -> Result<(), EvalError> {
+        let addr = self.lookup(id)?;
+        self.write(&addr, &[], value)
+    }
+
+    pub fn get_address(&self, id: &Identifier) -> Result<Address, EvalError> {
+        self.lookup(id)
+    }
+
+    pub fn read(&self, addr: &Address) -> Result<Value, EvalError> {
+        match addr {
+            Address::StackAddress(index, id) => match self.stack.get(*index) {
+                Some(ns) => ns.find(id),
+                None => Err(EvalError::DanglingReference(id.clone())),
+            },
+            Address::HeapAddress(offset) => self.heap.read(*offset),
+        }
+    }
+
+    pub fn write(&mut self, addr: &Address, path: &[Identifier], value: Value) -> Result<(), EvalError> {
+        match addr {
+            Address::StackAddress(index, id) => match self.stack.get_mut(*index) {
+                Some(ns) => ns.set_field(id, path, value)?,
+                None => return Err(EvalError::DanglingReference(id.clone())),
+            },
+            Address::HeapAddress(offset) => self.heap.write(*offset, path, value)?,
+        }
+        self.changes += 1;
+        self.trace_write(addr);
+        Ok(())
+    }
+
+    pub fn is_mutable(&self, addr: &Address) -> Result<bool, EvalError> {
+        match addr {
+            Address::StackAddress(index, id) => match self.stack.get(*index) {
+                Some(ns) => ns.is_mutable(id),
+                None => Err(EvalError::DanglingReference(id.clone())),
+            },
+            Address::HeapAddress(offset) => self.heap.is_mutable(*offset),
+        }
+    }
+
+    pub fn declare_struct(&mut self, s: Rc<Structure>) -> Result<(), EvalError> {
+        if self.structs.contains_key(&s.name) {
+            return Err(EvalError::AlreadyDefined(s.name.clone()));
+        }
+        self.structs.insert(s.name.clone(), s);
+        self.changes += 1;
+        Ok(())
+    }
+
+    pub fn get_struct(&self, name: &Identifier) -> Result<Rc<Structure>, EvalError> {
+        self.structs.get(name).cloned().ok_or(EvalError::Undefined(name.clone()))
+    }
+
+    pub fn declare_method(&mut self, ty: &Identifier, fun: Rc<Function>) -> Result<(), EvalError> {
+        self.get_struct(ty)?;
+        let methods = self.methods.entry(ty.clone()).or_default();
+        if methods.contains_key(&fun.name) {
+            return Err(EvalError::AlreadyDefined(fun.name.clone()));
+        }
+        methods.insert(fun.name.clone(), fun);
+        self.changes += 1;
+        Ok(())
+    }
+
+    // Structures déclarées, triées par nom
+    pub fn structs(&self) -> Vec<Rc<Structure>> {
+        let mut structs: Vec<_> = self.structs.values().cloned().collect();
+        structs.sort_by_key(|s| s.name.to_string());
+        structs
+    }
+
+    // Méthodes de chaque structure, triées par nom
+    pub fn methods(&self) -> Vec<(Identifier, Vec<Rc<Function>>)> {
+        let mut methods: Vec<_> = self.methods.iter().map(|(ty, funs)| {
+            let mut funs: Vec<_> = funs.values().cloned().collect();
+            funs.sort_by_key(|f| f.name.to_string());
+            (ty.clone(), funs)
+        }).collect();
+        methods.sort_by_key(|(ty, _)| ty.to_string());
+        methods
+    }
+
+    pub fn get_method(&self, ty: &Identifier, name: &Identifier) -> Option<Rc<Function>> {
+        self.methods.get(ty).and_then(|methods| methods.get(name)).cloned()
+    }
+}
+
+// Les NameSpace de la pile, du global au plus récent
+impl std::fmt::Display for NameSpaceStack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let namespaces: Vec<String> = self.stack.iter().enumerate().map(|(index, ns)| {
+            let cells = ns.to_string();
+            if cells.is_empty() {
+                format!("[{}] (empty)", index)
+            } else {
+                format!("[{}]\n{}", index, cells.lines().map(|l| format!("  {}", l)).collect::<Vec<_>>().join("\n"))
+            }
+        }).collect();
+        write!(f, "{}", namespaces.join("\n"))
     }
-}
\ No newline at end of file
+}
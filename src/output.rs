@@ -0,0 +1,44 @@
+use std::cell::RefCell;
+use std::io::Write;
+
+// Sorties des macros `print!`, `println!` et `eprintln!`.
+// La sortie standard peut être capturée, par exemple pour la joindre aux objets du mode `--json`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+thread_local! {
+    static CAPTURE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+pub fn write(stream: Stream, text: &str) {
+    match stream {
+        Stream::Stdout => {
+            let captured = CAPTURE.with(|capture| match capture.borrow_mut().as_mut() {
+                Some(buffer) => {
+                    buffer.push_str(text);
+                    true
+                },
+                None => false,
+            });
+            if !captured {
+                let mut stdout = std::io::stdout();
+                let _ = stdout.write_all(text.as_bytes());
+                let _ = stdout.flush();
+            }
+        },
+        Stream::Stderr => {
+            let _ = std::io::stderr().write_all(text.as_bytes());
+        },
+    }
+}
+
+// Exécute `f` en retenant ce qu'il écrit sur la sortie standard
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, String) {
+    let previous = CAPTURE.with(|capture| capture.replace(Some(String::new())));
+    let res = f();
+    let text = CAPTURE.with(|capture| capture.replace(previous)).unwrap_or_default();
+    (res, text)
+}
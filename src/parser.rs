@@ -1,17 +1,140 @@
 use std::fmt::{self, Display};
+use pest::error::{ErrorVariant, LineColLocation};
+use crate::parsing::utils::Rule;
+use crate::parsing::span::snippet;
 
 #[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
 pub enum ParseError {
-    CannotParse,
+    // Position (à partir de 1) de l'erreur, ligne fautive et éléments attendus à cet endroit
+    CannotParse{line: usize, column: usize, source_line: String, expected: Vec<String>},
     SyntaxNotSupported,
 }
 
+// Règles par lesquelles une expression peut commencer
+const EXPR_START: [Rule; 20] = [
+    Rule::integer, Rule::boolean, Rule::string, Rule::macro_call, Rule::macro_name, Rule::unit, Rule::ptrnew, Rule::conditional_expr, Rule::if_expr,
+    Rule::closure, Rule::struct_lit, Rule::path, Rule::self_kw, Rule::identifier, Rule::deref,
+    Rule::ampersand, Rule::lexpr, Rule::PTR, Rule::move_kw, Rule::paren_expr,
+];
+
+const BIN_OPS: [Rule; 15] = [
+    Rule::ADD, Rule::SUBTRACT, Rule::MULTIPLY, Rule::DIVIDE, Rule::MODULO, Rule::EQQUALS, Rule::NEQ,
+    Rule::LEQ, Rule::GEQ, Rule::LOWER, Rule::GREATER, Rule::AND, Rule::OR, Rule::RANGE_INCL, Rule::RANGE,
+];
+
+fn describe(rule: &Rule) -> String {
+    use Rule::*;
+    let s = match rule {
+        EOI => "end of input",
+        ADD => "`+`",
+        SUBTRACT => "`-`",
+        MULTIPLY => "`*`",
+        DIVIDE => "`/`",
+        MODULO => "`%`",
+        EQQUALS => "`==`",
+        NEQ => "`!=`",
+        LEQ => "`<=`",
+        GEQ => "`>=`",
+        LOWER => "`<`",
+        GREATER => "`>`",
+        AND => "`&&`",
+        OR => "`||`",
+        RANGE_INCL => "`..=`",
+        RANGE => "`..`",
+        PTR => "`Ptr`",
+        unit => "`()`",
+        ptrnew => "`Ptr::new()`",
+        self_kw => "`self`",
+        mut_kw => "`mut`",
+        move_kw => "`move`",
+        trailing_semicolon | separator => "`;`",
+        if_expr => "`if` expression",
+        struct_lit => "struct literal",
+        string => "string literal",
+        macro_call | macro_name => "macro call",
+        macro_arg => "macro argument",
+        field_init => "field initializer",
+        deref => "dereference",
+        ampersand => "reference",
+        method_call => "method call",
+        field => "field access",
+        field_decl => "field declaration",
+        attribute => "attribute",
+        bin_op => "operator",
+        receiver => "`self`",
+        param | mut_param => "parameter",
+        expr => "expression",
+        lexpr => "assignable expression",
+        instr => "instruction",
+        instrs => "block",
+        type_expr => "type",
+        ret_type => "return type",
+        _ => return format!("{:?}", rule).replace('_', " "),
+    };
+    s.to_string()
+}
+
+// Remplace les règles de `group` par `by`, seulement si la première règle du groupe est attendue
+// (un `identifier` attendu seul ne doit pas devenir « expression »)
+fn collapse(rules: &mut Vec<Rule>, group: &[Rule], by: Rule) {
+    if !rules.contains(&group[0]) {
+        return;
+    }
+    if let Some(i) = rules.iter().position(|r| group.contains(r)) {
+        rules.retain(|r| !group.contains(r));
+        rules.insert(i.min(rules.len()), by);
+    }
+}
+
+// Les débuts d'expression et les opérateurs sont regroupés pour garder la liste lisible
+fn expected(positives: &[Rule]) -> Vec<String> {
+    let mut rules: Vec<Rule> = positives.to_vec();
+    collapse(&mut rules, &EXPR_START, Rule::expr);
+    collapse(&mut rules, &BIN_OPS, Rule::bin_op);
+    let mut acc: Vec<String> = vec![];
+    for s in rules.iter().map(describe) {
+        if !acc.contains(&s) {
+            acc.push(s);
+        }
+    }
+    acc
+}
+
+impl From<pest::error::Error<Rule>> for ParseError {
+    fn from(e: pest::error::Error<Rule>) -> Self {
+        let (line, column) = match e.line_col {
+            LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+        };
+        let expected = match &e.variant {
+            ErrorVariant::ParsingError { positives, .. } => expected(positives),
+            ErrorVariant::CustomError { .. } => vec![],
+        };
+        ParseError::CannotParse{line, column, source_line: e.line().trim_end().to_string(), expected}
+    }
+}
+
+impl ParseError {
+    // Nom de la variante, pour les sorties destinées aux programmes
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParseError::CannotParse { .. } => "CannotParse",
+            ParseError::SyntaxNotSupported => "SyntaxNotSupported",
+        }
+    }
+}
+
 impl Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use ParseError::*;
         match self {
-            CannotParse => write!(f, "Cannot parse"),
+            CannotParse{line, column, source_line, expected} => {
+                write!(f, "Cannot parse, line {}, column {}\n{}", line, column, snippet(*line, *column, 1, source_line))?;
+                if !expected.is_empty() {
+                    write!(f, "\nExpected: {}", expected.join(", "))?;
+                }
+                Ok(())
+            }
             SyntaxNotSupported => write!(f, "Syntax not supported"),
         }
     }
@@ -19,4 +142,4 @@ impl Display for ParseError {
 
 pub trait Parse {
     fn parse(input: &str) -> Result<Self, ParseError> where Self: Sized;
-}
\ No newline at end of file
+}
@@ -4,11 +4,17 @@ extern crate lazy_static;
 
 pub mod binop;
 pub mod expression;
+pub mod format;
+pub mod function;
 pub mod instruction;
 pub mod leftexpression;
+pub mod macro_call;
 pub mod ptr_kind;
+pub mod structure;
 pub mod utils;
 pub mod parsedvalue;
+pub mod program;
+pub mod span;
 pub(crate) mod value;
 
 
@@ -12,7 +12,9 @@ pub enum Binop {
     Eq,
     Neq,
     And,
-    Or
+    Or,
+    Range,
+    RangeInclusive,
 }
 
 use std::fmt::{self, Display};
@@ -34,6 +36,8 @@ impl Display for Binop {
             Neq => write!(f, "!="),
             And => write!(f, "&&"),
             Or => write!(f, "||"),
+            Range => write!(f, ".."),
+            RangeInclusive => write!(f, "..="),
         }
     }
 }
@@ -48,7 +52,7 @@ use crate::parser::{Parse, ParseError};
 impl Parse for Binop {
 
     fn parse(input: &str) -> Result<Self, ParseError> {
-        match PestParser::parse(Rule::start_rule_binop, &input) {
+        match PestParser::parse(Rule::start_rule_binop, input) {
             Ok(mut pairs) => {
                 let first_rule = pairs.next().unwrap();
                 match first_rule.as_rule() {
@@ -58,7 +62,7 @@ impl Parse for Binop {
                     _ => { panic!("the grammar is not as expected") }
                 }                
             },
-            Err(_e) => { Err(ParseError::CannotParse) }
+            Err(e) => { Err(ParseError::from(e)) }
         }
     }
 }
\ No newline at end of file
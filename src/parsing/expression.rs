@@ -2,6 +2,13 @@ use super::leftexpression::LeftExpression;
 use super::binop::Binop;
 
 use super::parsedvalue::ParsedValue;
+use super::function::{Function, source_type};
+use super::instruction::Instruction;
+use super::macro_call::{Macro, MacroArg};
+use super::format;
+use super::span::Span;
+use crate::r#type::Type;
+use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
@@ -17,6 +24,24 @@ pub enum Expression {
     },
     Deref(Box<Expression>),
     AmpersAnd(Box<Expression>),
+    StructLit(Identifier, Vec<(Identifier, Expression)>),
+    Field(Box<Expression>, Identifier),
+    Path(Identifier, Identifier),
+    Call(Box<Expression>, Vec<Expression>),
+    MethodCall(Box<Expression>, Identifier, Vec<Expression>),
+    Closure{is_move: bool, fun: Rc<Function>},
+    If{
+        cond: Box<Expression>,
+        cond_true: Box<Instruction>,
+        cond_false: Option<Box<Instruction>>,
+    },
+    Macro(Macro, Vec<MacroArg>),
+    // Expression accompagnée de sa portion du texte source
+    Spanned(Span, Box<Expression>),
+}
+
+fn join(exprs: &[Expression]) -> String {
+    exprs.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(", ")
 }
 
 
@@ -35,6 +60,100 @@ impl Display for Expression {
             Identifier(i) => write!(f, "{}", i),
             Deref(e) => write!(f, "*{}", e),
             AmpersAnd(e) => write!(f, "&{}", e),
+            StructLit(name, fields) => {
+                let fields: Vec<String> = fields.iter().map(|(id, e)| format!("{}: {}", id, e)).collect();
+                write!(f, "{} {{{}}}", name, fields.join(", "))
+            },
+            Field(e, id) => write!(f, "{}.{}", e, id),
+            Path(ty, id) => write!(f, "{}::{}", ty, id),
+            Call(e, args) => write!(f, "{}({})", e, join(args)),
+            MethodCall(e, id, args) => write!(f, "{}.{}({})", e, id, join(args)),
+            Closure { is_move, fun } => {
+                let params: Vec<String> = fun.params.iter().map(|p| p.to_string()).collect();
+                write!(f, "{}|{}| ", if *is_move { "move " } else { "" }, params.join(", "))?;
+                match (&fun.ret, &fun.body) {
+                    (Type::Infer, Instruction::Expr(e)) => write!(f, "{}", e),
+                    (Type::Infer, body) => write!(f, "{}", body),
+                    (ret, body) => write!(f, "-> {} {}", source_type(ret), body),
+                }
+            },
+            If { cond, cond_true, cond_false: None } => write!(f, "if {} {}", cond, cond_true),
+            If { cond, cond_true, cond_false: Some(cond_false) } => {
+                write!(f, "if {} {} else {}", cond, cond_true, cond_false)
+            },
+            Macro(name, args) => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            },
+            // Le texte d'origine plutôt qu'une reconstruction entièrement parenthésée
+            Spanned(span, _) => write!(f, "{}", span.as_str()),
+        }
+    }
+}
+
+impl Expression {
+    // Identifiants auxquels l'expression fait référence
+    pub fn identifiers(&self, acc: &mut Vec<Identifier>) {
+        use Expression::*;
+        match self {
+            Const(_) | NewPtr | Path(_, _) => (),
+            Identifier(id) => acc.push(id.clone()),
+            ValueAt(lexpr) => lexpr.identifiers(acc),
+            BinOp(lhs, _, rhs) => {
+                lhs.identifiers(acc);
+                rhs.identifiers(acc);
+            },
+            Conditional { cond, cond_true, cond_false } => {
+                cond.identifiers(acc);
+                cond_true.identifiers(acc);
+                cond_false.identifiers(acc);
+            },
+            Deref(e) | AmpersAnd(e) | Field(e, _) => e.identifiers(acc),
+            StructLit(_, fields) => fields.iter().for_each(|(_, e)| e.identifiers(acc)),
+            Call(e, args) | MethodCall(e, _, args) => {
+                e.identifiers(acc);
+                args.iter().for_each(|e| e.identifiers(acc));
+            },
+            Closure { fun, .. } => fun.body.identifiers(acc),
+            If { cond, cond_true, cond_false } => {
+                cond.identifiers(acc);
+                cond_true.identifiers(acc);
+                if let Some(cond_false) = cond_false {
+                    cond_false.identifiers(acc);
+                }
+            },
+            Macro(_, args) => {
+                args.iter().for_each(|arg| arg.expr.identifiers(acc));
+                // `{x}` sans argument `x = ...` désigne la variable `x`
+                let named: Vec<_> = args.iter().filter_map(|arg| arg.name.clone()).collect();
+                if let Some(pieces) = args.first().and_then(|arg| arg.expr.as_str()).and_then(|s| format::parse(s).ok()) {
+                    acc.extend(format::implicit_names(&pieces, &named));
+                }
+            },
+            Spanned(_, e) => e.identifiers(acc),
+        }
+    }
+
+    // L'expression sans sa portion du texte source
+    pub fn unspanned(&self) -> &Expression {
+        match self {
+            Expression::Spanned(_, e) => e.unspanned(),
+            e => e,
+        }
+    }
+
+    // Chaîne littérale, comme le format d'une macro
+    pub fn as_str(&self) -> Option<&str> {
+        match self.unspanned() {
+            Expression::Const(ParsedValue::Str(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Expression::Spanned(span, _) => Some(span),
+            _ => None,
         }
     }
 }
@@ -59,22 +178,22 @@ impl From<Expression> for Result<Expression, ParseError> {
                     cond_false: Box::new(Self::from(*cond_false)?)
                 })
             }
+            Expression::Spanned(_, e) => Self::from(*e),
             _ => { Err(ParseError::SyntaxNotSupported) }
         }
     }
 }
 
-use pest::Parser;
 use crate::identifier::Identifier;
 
-use super::utils::{ PestParser, Rule, parse_expr};
+use super::utils::{ Rule, parse_expr, parse_source};
 use crate::parser::{ Parse, ParseError};
 
 
 impl Parse for Expression {
 
     fn parse(input: &str) -> Result<Self, ParseError> {
-        match PestParser::parse(Rule::start_rule_expr, &input) {
+        match parse_source(Rule::start_rule_expr, input) {
             Ok(mut pairs) => {
                 let first_rule = pairs.next().unwrap();
                 match first_rule.as_rule() {
@@ -84,7 +203,7 @@ impl Parse for Expression {
                     _ => { panic!("the grammar is not as expected") }
                 }
             },
-            Err(_e) => { Err(ParseError::CannotParse) }
+            Err(e) => { Err(e) }
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,178 @@
+use crate::identifier::Identifier;
+use crate::parsing::value::Value;
+
+// Chaîne de format des macros `println!`, `format!`... découpée en texte et en emplacements `{}`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Piece {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+// Argument désigné par un emplacement : le suivant (`{}`), par position (`{0}`) ou par nom (`{x}`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgRef {
+    Next,
+    Index(usize),
+    Name(Identifier),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// `{arg:fill align 0 width .precision ?}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    pub arg: ArgRef,
+    pub fill: char,
+    pub align: Option<Align>,
+    pub zero: bool,
+    pub width: Option<usize>,
+    pub precision: Option<usize>,
+    pub debug: bool,
+}
+
+fn align(c: char) -> Option<Align> {
+    match c {
+        '<' => Some(Align::Left),
+        '^' => Some(Align::Center),
+        '>' => Some(Align::Right),
+        _ => None,
+    }
+}
+
+fn number(spec: &str) -> (Option<usize>, &str) {
+    let end = spec.find(|c: char| !c.is_ascii_digit()).unwrap_or(spec.len());
+    (spec[..end].parse().ok(), &spec[end..])
+}
+
+fn parse_placeholder(inner: &str) -> Result<Placeholder, String> {
+    let (arg, spec) = inner.split_once(':').unwrap_or((inner, ""));
+    let arg = match arg.trim() {
+        "" => ArgRef::Next,
+        arg if arg.chars().all(|c| c.is_ascii_digit()) => match arg.parse() {
+            Ok(index) => ArgRef::Index(index),
+            Err(_) => return Err(format!("invalid reference to positional argument {}", arg)),
+        },
+        arg if arg.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && arg.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => ArgRef::Name(Identifier::from(arg)),
+        arg => return Err(format!("invalid argument name `{}`", arg)),
+    };
+    let mut placeholder = Placeholder { arg, fill: ' ', align: None, zero: false, width: None, precision: None, debug: false };
+    let mut rest = spec;
+    // Le caractère de remplissage n'est reconnu que devant un alignement
+    let mut chars = rest.chars();
+    match (chars.next(), chars.next()) {
+        (Some(fill), Some(c)) if align(c).is_some() => {
+            placeholder.fill = fill;
+            placeholder.align = align(c);
+            rest = &rest[fill.len_utf8() + 1..];
+        },
+        (Some(c), _) if align(c).is_some() => {
+            placeholder.align = align(c);
+            rest = &rest[1..];
+        },
+        _ => (),
+    }
+    if let Some(r) = rest.strip_prefix('0') {
+        placeholder.zero = true;
+        rest = r;
+    }
+    (placeholder.width, rest) = number(rest);
+    if let Some(r) = rest.strip_prefix('.') {
+        (placeholder.precision, rest) = number(r);
+        if placeholder.precision.is_none() {
+            return Err(format!("missing precision in `{{{}}}`", inner));
+        }
+    }
+    if let Some(r) = rest.strip_prefix('?') {
+        placeholder.debug = true;
+        rest = r;
+    }
+    if !rest.is_empty() {
+        return Err(format!("unknown format specifier `{}` in `{{{}}}`", rest, inner));
+    }
+    Ok(placeholder)
+}
+
+pub fn parse(format: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                text.push('{');
+            },
+            '{' => {
+                let rest = chars.as_str();
+                let end = rest.find('}').ok_or("unmatched `{` in format string")?;
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(Piece::Placeholder(parse_placeholder(&rest[..end])?));
+                chars = rest[end + 1..].chars();
+            },
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                text.push('}');
+            },
+            '}' => return Err("unmatched `}` in format string".to_string()),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+// Arguments nommés de la chaîne absents de la liste : des variables de l'environnement, comme `{x}` en Rust
+pub fn implicit_names(pieces: &[Piece], named: &[Identifier]) -> Vec<Identifier> {
+    let mut names: Vec<Identifier> = vec![];
+    for piece in pieces {
+        if let Piece::Placeholder(Placeholder { arg: ArgRef::Name(id), .. }) = piece {
+            if !named.contains(id) && !names.contains(id) {
+                names.push(id.clone());
+            }
+        }
+    }
+    names
+}
+
+fn pad(s: String, placeholder: &Placeholder, numeric: bool) -> String {
+    let len = s.chars().count();
+    let width = match placeholder.width {
+        Some(width) if width > len => width,
+        _ => return s,
+    };
+    // `{:05}` : les zéros se placent après le signe
+    if placeholder.zero && numeric {
+        let (sign, digits) = s.split_at(if s.starts_with('-') { 1 } else { 0 });
+        return format!("{}{}{}", sign, "0".repeat(width - len), digits);
+    }
+    let fill = |n: usize| placeholder.fill.to_string().repeat(n);
+    // Comme en Rust, les nombres sont alignés à droite et le reste à gauche
+    let default = if numeric { Align::Right } else { Align::Left };
+    match placeholder.align.unwrap_or(default) {
+        Align::Left => format!("{}{}", s, fill(width - len)),
+        Align::Right => format!("{}{}", fill(width - len), s),
+        Align::Center => {
+            let left = (width - len) / 2;
+            format!("{}{}{}", fill(left), s, fill(width - len - left))
+        },
+    }
+}
+
+pub fn render(placeholder: &Placeholder, value: &Value) -> String {
+    let mut s = if placeholder.debug { value.debug() } else { value.to_string() };
+    // Pas de flottants : comme en Rust, la précision ne tronque que les chaînes, et jamais un nombre
+    if let (Some(precision), Value::Str(_), false) = (placeholder.precision, value, placeholder.debug) {
+        s = s.chars().take(precision).collect();
+    }
+    pad(s, placeholder, matches!(value, Value::Integer(_)))
+}
@@ -0,0 +1,153 @@
+use std::fmt::{self, Display};
+use std::rc::Rc;
+
+use crate::identifier::Identifier;
+use crate::memory::Address;
+use crate::parsing::instruction::Instruction;
+use crate::parsing::value::Value;
+use crate::r#type::Type;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Receiver {
+    Value,
+    Ref,
+    RefMut,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub id: Identifier,
+    pub mutable: bool,
+    pub ty: Type,
+}
+
+#[derive(Debug)]
+pub struct Function {
+    // Attributs de la déclaration, comme `#[test]`
+    pub attributes: Vec<Identifier>,
+    pub name: Identifier,
+    pub receiver: Option<Receiver>,
+    pub params: Vec<Param>,
+    pub ret: Type,
+    pub body: Instruction,
+}
+
+// Deux fonctions sont égales si et seulement si elles proviennent de la même déclaration
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Function {
+    // Type de la fonction, le receveur éventuel compte comme premier paramètre
+    pub fn get_type(&self, self_type: Option<&Type>) -> Type {
+        let mut params = vec![];
+        match (self.receiver, self_type) {
+            (Some(Receiver::Value), Some(t)) => params.push(t.clone()),
+            (Some(_), _) => params.push(Type::Address),
+            _ => (),
+        }
+        params.extend(self.params.iter().map(|p| p.ty.clone()));
+        Type::Function(params, Box::new(self.ret.clone()))
+    }
+
+    pub fn is_test(&self) -> bool {
+        self.attributes.contains(&Identifier::from("test"))
+    }
+}
+
+// Variable de l'environnement capturée par une closure à sa création.
+// Une capture par référence retient l'identifiant du NameSpace visé.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Capture {
+    ByRef(Address, usize),
+    ByValue(Value),
+}
+
+#[derive(Debug)]
+pub struct Closure {
+    pub fun: Rc<Function>,
+    pub captures: Vec<(Identifier, Capture)>,
+}
+
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// Fonction de l'hôte, écrite en Rust et enregistrée par `Interpreter::register`.
+// Une erreur renvoyée par la fonction devient une `EvalError::NativeError`.
+pub type NativeCallback = dyn Fn(&[Value]) -> Result<Value, String>;
+
+pub struct NativeFunction {
+    pub name: Identifier,
+    // `Type::Infer` accepte un argument de n'importe quel type
+    pub params: Vec<Type>,
+    pub ret: Type,
+    pub fun: Box<NativeCallback>,
+}
+
+impl NativeFunction {
+    pub fn get_type(&self) -> Type {
+        Type::Function(self.params.clone(), Box::new(self.ret.clone()))
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// Les types sont réécrits dans la syntaxe acceptée par la grammaire
+pub fn source_type(t: &Type) -> String {
+    match t {
+        Type::Unit => "()".to_string(),
+        _ => t.to_string(),
+    }
+}
+
+impl Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.mutable {
+            write!(f, "mut ")?;
+        }
+        match self.ty {
+            Type::Infer => write!(f, "{}", self.id),
+            _ => write!(f, "{}: {}", self.id, source_type(&self.ty)),
+        }
+    }
+}
+
+impl Display for Receiver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Receiver::Value => write!(f, "self"),
+            Receiver::Ref => write!(f, "&self"),
+            Receiver::RefMut => write!(f, "&mut self"),
+        }
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut params: Vec<String> = self.receiver.iter().map(|r| r.to_string()).collect();
+        params.extend(self.params.iter().map(|p| p.to_string()));
+        for attribute in &self.attributes {
+            write!(f, "#[{}] ", attribute)?;
+        }
+        write!(f, "fn {}({})", self.name, params.join(", "))?;
+        if self.ret != Type::Unit {
+            write!(f, " -> {}", source_type(&self.ret))?;
+        }
+        write!(f, " {}", self.body)
+    }
+}
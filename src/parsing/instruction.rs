@@ -1,20 +1,57 @@
 use crate::parsing::expression::Expression;
 use crate::parsing::leftexpression::LeftExpression;
+use crate::parsing::function::Function;
+use crate::parsing::structure::Structure;
+use crate::parsing::span::Span;
 use crate::identifier::Identifier;
+use std::rc::Rc;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
     Expr(Expression),
     Let{id:Identifier, mutable:bool, expr:Expression},
-    Block(Vec<Instruction>),
-    IfElse{
-        cond: Expression,
-        cond_true: Box<Instruction>,
-        cond_false: Box<Instruction>,
-    },
+    // Sans expression finale (`tail`), un bloc vaut ()
+    Block{instrs: Vec<Instruction>, tail: bool},
     WriteAt(LeftExpression, Expression),
     While(Expression, Box<Instruction>),
+    For(Identifier, Expression, Box<Instruction>),
+    Break,
+    Continue,
     Free(LeftExpression),
+    Fn(Rc<Function>),
+    Struct(Rc<Structure>),
+    Impl(Identifier, Vec<Rc<Function>>),
+    Spanned(Span, Box<Instruction>),
+}
+
+impl Instruction {
+    // Identifiants auxquels l'instruction fait référence
+    pub fn identifiers(&self, acc: &mut Vec<Identifier>) {
+        use Instruction::*;
+        match self {
+            Expr(expr) | Let { expr, .. } => expr.identifiers(acc),
+            Block { instrs, .. } => instrs.iter().for_each(|i| i.identifiers(acc)),
+            WriteAt(lexpr, expr) => {
+                lexpr.identifiers(acc);
+                expr.identifiers(acc);
+            },
+            While(cond, instr) | For(_, cond, instr) => {
+                cond.identifiers(acc);
+                instr.identifiers(acc);
+            },
+            Free(lexpr) => lexpr.identifiers(acc),
+            Break | Continue | Fn(_) | Struct(_) | Impl(_, _) => (),
+            Spanned(_, instr) => instr.identifiers(acc),
+        }
+    }
+
+    // L'instruction sans sa portion du texte source
+    pub fn unspanned(&self) -> &Instruction {
+        match self {
+            Instruction::Spanned(_, instr) => instr.unspanned(),
+            instr => instr,
+        }
+    }
 }
 
 use std::fmt::Display;
@@ -30,11 +67,9 @@ impl Display for Instruction {
                     write!(f, "let {} = {}", id, expr)
                 }
             },
-            Block(instrs) => {
-                write!(f, "{{{}}}", instrs.into_iter().map(|x| x.to_string()).collect::<Vec<_>>().join(";"))
-            },
-            IfElse{cond, cond_true, cond_false} => {
-                write!(f, "if {} {} else {}", cond, cond_true, cond_false)
+            Block { instrs, tail } => {
+                let semicolon = if *tail || instrs.is_empty() { "" } else { ";" };
+                write!(f, "{{{}{}}}", instrs.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(";"), semicolon)
             },
             WriteAt(lexpr, expr) => {
                 write!(f, "{} = {}", lexpr, expr)
@@ -42,9 +77,20 @@ impl Display for Instruction {
             While(cond, instr) => {
                 write!(f, "while {} {}", cond, instr)
             },
+            For(id, iter, instr) => {
+                write!(f, "for {} in {} {}", id, iter, instr)
+            },
+            Break => write!(f, "break"),
+            Continue => write!(f, "continue"),
             Free(lexpr) => {
                 write!(f, "free {}", lexpr)
             },
+            Fn(fun) => write!(f, "{}", fun),
+            Struct(s) => write!(f, "{}", s),
+            Impl(name, funs) => {
+                write!(f, "impl {} {{{}}}", name, funs.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" "))
+            },
+            Spanned(span, _) => write!(f, "{}", span.as_str()),
         }
     }
 }
@@ -56,36 +102,30 @@ impl From<Instruction> for Result<Instruction, ParseError> {
             Instruction::Let{id, mutable, expr} => {
                 Ok(Instruction::Let{id, mutable, expr: Expression::parse(&expr.to_string())?})
             },
-            Instruction::Block(instrs) => {
-                let instrs: Result<Vec<Instruction>, ParseError> = instrs.into_iter().map(|x| <_>::from(x)).collect();
-                Ok(Instruction::Block(instrs?))
+            Instruction::Block { instrs, tail } => {
+                let instrs: Result<Vec<Instruction>, ParseError> = instrs.into_iter().map(<_>::from).collect();
+                Ok(Instruction::Block { instrs: instrs?, tail })
             },
             Instruction::WriteAt(lexpr, expr) => {
                 Ok(Instruction::WriteAt(lexpr, Expression::parse(&expr.to_string())?))
             },
-            Instruction::IfElse{cond, cond_true, cond_false} => {
-                let cond = Expression::parse(&cond.to_string())?;
-                let cond_true = Box::new(Instruction::parse(&cond_true.to_string())?);
-                let cond_false = Box::new(Instruction::parse(&cond_false.to_string())?);
-                Ok(Instruction::IfElse{cond, cond_true, cond_false})
-            },
             Instruction::While(cond, instr) => {
                 let cond = Expression::parse(&cond.to_string())?;
                 let instr = Box::new(Instruction::parse(&instr.to_string())?);
                 Ok(Instruction::While(cond, instr))
             },
+            Instruction::Spanned(_, instr) => <_>::from(*instr),
             _ => { Err(ParseError::SyntaxNotSupported) }
         }
     }
 }
 
-use pest::Parser;
 use crate::parser::{ParseError, Parse};
-use super::utils::{PestParser, Rule, parse_instr};
+use super::utils::{Rule, parse_instr, parse_source};
 
 impl Parse for Instruction {
     fn parse(input: &str) -> Result<Self, ParseError> {
-        match PestParser::parse(Rule::start_rule_instr, &input) {
+        match parse_source(Rule::start_rule_instr, input) {
             Ok(mut pairs) => {
                 let first_rule = pairs.next().unwrap();
                 match first_rule.as_rule() {
@@ -95,7 +135,7 @@ impl Parse for Instruction {
                     _ => { panic!("the grammar is not as expected") }
                 }
             },
-            Err(_e) => { Err(ParseError::CannotParse) }
+            Err(e) => { Err(e) }
         }
     }
 }
\ No newline at end of file
@@ -6,6 +6,16 @@ use crate::identifier::Identifier;
 pub enum LeftExpression {
     Identifier(Identifier),
     Star(Box<LeftExpression>),
+    Field(Box<LeftExpression>, Identifier),
+}
+
+impl LeftExpression {
+    pub fn identifiers(&self, acc: &mut Vec<Identifier>) {
+        match self {
+            LeftExpression::Identifier(id) => acc.push(id.clone()),
+            LeftExpression::Star(lexpr) | LeftExpression::Field(lexpr, _) => lexpr.identifiers(acc),
+        }
+    }
 }
 
 impl Display for LeftExpression {
@@ -14,6 +24,7 @@ impl Display for LeftExpression {
         match self {
             Identifier(id) => {write!(f, "{}", id)? },
             Star(lexpr) => {write!(f, "*{}", lexpr)?},
+            Field(lexpr, id) => {write!(f, "{}.{}", lexpr, id)?},
         };
         Ok(())
     }
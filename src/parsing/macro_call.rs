@@ -0,0 +1,64 @@
+use std::fmt::{self, Display};
+
+use crate::identifier::Identifier;
+use crate::parsing::expression::Expression;
+
+// Macros prédéfinies, appelées avec `nom!(...)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Macro {
+    Print,
+    Println,
+    Eprintln,
+    Format,
+    Assert,
+    AssertEq,
+    AssertNe,
+    Panic,
+}
+
+impl Macro {
+    pub fn from_name(name: &str) -> Option<Macro> {
+        match name {
+            "print" => Some(Macro::Print),
+            "println" => Some(Macro::Println),
+            "eprintln" => Some(Macro::Eprintln),
+            "format" => Some(Macro::Format),
+            "assert" => Some(Macro::Assert),
+            "assert_eq" => Some(Macro::AssertEq),
+            "assert_ne" => Some(Macro::AssertNe),
+            "panic" => Some(Macro::Panic),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Macro {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Macro::Print => write!(f, "print!"),
+            Macro::Println => write!(f, "println!"),
+            Macro::Eprintln => write!(f, "eprintln!"),
+            Macro::Format => write!(f, "format!"),
+            Macro::Assert => write!(f, "assert!"),
+            Macro::AssertEq => write!(f, "assert_eq!"),
+            Macro::AssertNe => write!(f, "assert_ne!"),
+            Macro::Panic => write!(f, "panic!"),
+        }
+    }
+}
+
+// Argument d'une macro, éventuellement nommé : `println!("{x}", x = 1)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacroArg {
+    pub name: Option<Identifier>,
+    pub expr: Expression,
+}
+
+impl Display for MacroArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} = {}", name, self.expr),
+            None => write!(f, "{}", self.expr),
+        }
+    }
+}
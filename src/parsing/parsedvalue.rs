@@ -1,10 +1,12 @@
 use std::fmt;
+use std::rc::Rc;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ParsedValue {
     Integer(isize),
     Boolean(bool),
     Unit,
+    Str(Rc<str>),
 }
 
 impl fmt::Display for ParsedValue {
@@ -14,6 +16,7 @@ impl fmt::Display for ParsedValue {
             ParsedValue::Integer(i) => write!(f, "{}", i),
             ParsedValue::Boolean(b) => write!(f, "{}", b),
             ParsedValue::Unit => write!(f, "()"),
+            ParsedValue::Str(s) => write!(f, "{:?}", s),
         }
     }
 }
\ No newline at end of file
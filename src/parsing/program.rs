@@ -0,0 +1,56 @@
+use std::fmt::{self, Display};
+
+
+use crate::error::EvalError;
+use crate::identifier::Identifier;
+use crate::namespacestack::NameSpaceStack;
+use crate::parser::{Parse, ParseError};
+use crate::parsing::instruction::Instruction;
+use crate::parsing::value::Value;
+use crate::typing::{self, TypeEnv};
+use super::utils::{Rule, parse_instr, parse_source};
+
+// Suite d'instructions exécutées dans le NameSpace courant, comme à la console
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program(pub Vec<Instruction>);
+
+impl Program {
+    pub fn exec(&self, nss: &mut NameSpaceStack) -> Result<(Option<Identifier>, Value), EvalError> {
+        let mut res = (None, Value::Unit);
+        for instr in &self.0 {
+            typing::check_branches(instr, &mut TypeEnv::new(), nss)?;
+            res = instr.exec(nss)?;
+        }
+        Ok(res)
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for instr in &self.0 {
+            writeln!(f, "{};", instr)?;
+        }
+        Ok(())
+    }
+}
+
+impl Parse for Program {
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        match parse_source(Rule::start_rule_program, input) {
+            Ok(mut pairs) => {
+                let first_rule = pairs.next().unwrap();
+                match first_rule.as_rule() {
+                    Rule::program => {
+                        let instrs: Result<Vec<Instruction>, ParseError> = first_rule.into_inner()
+                            .filter(|rule| rule.as_rule() == Rule::instr)
+                            .map(|rule| parse_instr(&mut rule.into_inner()))
+                            .collect();
+                        Ok(Program(instrs?))
+                    }
+                    _ => { panic!("the grammar is not as expected") }
+                }
+            },
+            Err(e) => { Err(e) }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::rc::Rc;
+
+// Portion du texte source (en octets) d'où provient un nœud de l'AST.
+// Le texte est partagé pour pouvoir souligner une erreur levée dans une fonction déclarée plus tôt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    source: Rc<str>,
+    start: usize,
+    end: usize,
+}
+
+impl Span {
+    pub fn new(source: Rc<str>, start: usize, end: usize) -> Self {
+        Span { source, start, end }
+    }
+
+    // Plus petite portion contenant les deux
+    pub fn join(&self, other: &Span) -> Span {
+        Span::new(self.source.clone(), self.start.min(other.start), self.end.max(other.end))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source[self.start..self.end]
+    }
+
+    // Ligne et colonne (à partir de 1) du début de la portion
+    pub fn line_col(&self) -> (usize, usize) {
+        let before = &self.source[..self.start];
+        let line = before.matches('\n').count() + 1;
+        let column = before[before.rfind('\n').map_or(0, |i| i + 1)..].chars().count() + 1;
+        (line, column)
+    }
+
+    // Ligne de la portion, soulignée jusqu'à la fin de la portion ou de la ligne
+    pub fn snippet(&self) -> String {
+        let (line, column) = self.line_col();
+        let source_line = self.source[self.start - (column - 1)..].lines().next().unwrap_or("");
+        let len = self.as_str().lines().next().unwrap_or("").chars().count();
+        snippet(line, column, len.max(1), source_line)
+    }
+}
+
+//   |
+// 2 | let x = 1 / (1 - 1)
+//   |             ^^^^^^^
+pub fn snippet(line: usize, column: usize, len: usize, source_line: &str) -> String {
+    // Les tabulations sont conservées pour que le soulignement reste aligné
+    let padding: String = source_line.chars().take(column - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let margin = " ".repeat(line.to_string().len());
+    format!("{} |\n{} | {}\n{} | {}{}", margin, line, source_line, margin, padding, "^".repeat(len))
+}
@@ -0,0 +1,24 @@
+use std::fmt::{self, Display};
+
+use crate::identifier::Identifier;
+use crate::parsing::function::source_type;
+use crate::r#type::Type;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Structure {
+    pub name: Identifier,
+    pub fields: Vec<(Identifier, Type)>,
+}
+
+impl Structure {
+    pub fn field_type(&self, field: &Identifier) -> Option<&Type> {
+        self.fields.iter().find(|(id, _)| id == field).map(|(_, t)| t)
+    }
+}
+
+impl Display for Structure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = self.fields.iter().map(|(id, t)| format!("{}: {}", id, source_type(t))).collect();
+        write!(f, "struct {} {{{}}}", self.name, fields.join(", "))
+    }
+}
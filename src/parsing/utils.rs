@@ -2,6 +2,8 @@
 // see https://pest.rs/book/ and https://pest.rs/book/examples/calculator.html
 use pest::iterators::{Pairs, Pair};
 use pest::pratt_parser::PrattParser;
+use pest::Parser;
+use std::cell::RefCell;
 
 #[derive(pest_derive::Parser)]
 #[grammar = "parsing/grammar.pest"]
@@ -13,9 +15,15 @@ use super::leftexpression::LeftExpression;
 use super::binop::Binop;
 use super::ptr_kind::PtrKind;
 use super::parsedvalue::ParsedValue;
+use super::function::{Function, Param, Receiver};
+use super::structure::Structure;
+use super::macro_call::{Macro, MacroArg};
+use super::span::Span;
 
 use crate::identifier::Identifier;
 use crate::parser::ParseError;
+use crate::r#type::Type;
+use std::rc::Rc;
 
 lazy_static::lazy_static! {
     static ref PRATT_PARSER: PrattParser<Rule> = {
@@ -24,25 +32,110 @@ lazy_static::lazy_static! {
 
         // Precedence is defined lowest to highest
         PrattParser::new()
+            .op(Op::infix(RANGE, Left) | Op::infix(RANGE_INCL, Left))
             // Addition and subtract have equal precedence
             .op(Op::infix(AND, Left) | Op::infix(OR, Left))
             .op(Op::infix(EQQUALS, Left) | Op::infix(NEQ, Left) | Op::infix(GEQ, Left) | Op::infix(LEQ, Left) | Op::infix(GREATER, Left) | Op::infix(LOWER, Left))
             .op(Op::infix(ADD, Left) | Op::infix(SUBTRACT, Left))
             .op(Op::infix(MULTIPLY, Left) | Op::infix(DIVIDE, Left) | Op::infix(MODULO, Left))
 //            .op(Op::prefix(unary_minus))
+            .op(Op::postfix(method_call) | Op::postfix(field) | Op::postfix(call))
     };
 }
 
+thread_local! {
+    // Texte en cours d'analyse, partagé par les portions des nœuds produits
+    static SOURCE: RefCell<Rc<str>> = RefCell::new(Rc::from(""));
+}
+
+// Point d'entrée des analyses dont les nœuds portent leur portion du texte source
+pub fn parse_source(rule: Rule, input: &str) -> Result<Pairs<'_, Rule>, ParseError> {
+    SOURCE.with(|source| *source.borrow_mut() = Rc::from(input));
+    PestParser::parse(rule, input).map_err(ParseError::from)
+}
+
+// Les blancs qui suivent une règle sont compris dans sa portion par pest
+fn span(pair: &Pair<Rule>) -> Span {
+    let source = SOURCE.with(|source| source.borrow().clone());
+    let start = pair.as_span().start();
+    Span::new(source, start, start + pair.as_str().trim_end().len())
+}
+
+fn spanned(span: Span, expr: Expression) -> Expression {
+    match expr {
+        Expression::Spanned(_, _) => expr,
+        expr => Expression::Spanned(span, Box::new(expr)),
+    }
+}
+
+// Les nœuds produits par `parse_expr` portent tous leur portion
+fn span_of(expr: &Expression) -> &Span {
+    expr.span().expect("parse_expr produces spanned expressions")
+}
+
 pub fn parse_lexpr(mut pairs: Pairs<Rule>) -> LeftExpression {
     let first_rule = pairs.next().unwrap();
-    match first_rule.as_rule() {
-        Rule::identifier => LeftExpression::Identifier(Identifier::from(first_rule.as_str())),
+    let lexpr = match first_rule.as_rule() {
+        Rule::identifier | Rule::self_kw => LeftExpression::Identifier(Identifier::from(first_rule.as_str())),
         Rule::deref => LeftExpression::Star(Box::new(parse_lexpr(first_rule.into_inner()))),
         Rule::lexpr => parse_lexpr(first_rule.into_inner()),
         _ => unreachable!()
+    };
+    // Accès aux champs : `p.x.y`
+    pairs.fold(lexpr, |lexpr, field| {
+        let id = Identifier::from(field.into_inner().next().unwrap().as_str());
+        LeftExpression::Field(Box::new(lexpr), id)
+    })
+}
+
+pub fn parse_type(pair: Pair<Rule>) -> Type {
+    let first_rule = pair.into_inner().next().unwrap();
+    match first_rule.as_rule() {
+        Rule::ref_type | Rule::PTR => Type::Address,
+        Rule::unit => Type::Unit,
+        Rule::identifier => match first_rule.as_str() {
+            "isize" => Type::Int,
+            "bool" => Type::Bool,
+            "String" => Type::Str,
+            "Range" => Type::Range,
+            name => Type::Struct(Identifier::from(name)),
+        },
+        _ => unreachable!("parse_type expected type, found {:?}", first_rule),
     }
 }
 
+fn parse_args(pair: Pair<Rule>) -> Vec<Expression> {
+    pair.into_inner().map(|arg| parse_expr(arg.into_inner())).collect()
+}
+
+
+// Contenu d'une chaîne littérale, séquences d'échappement remplacées
+fn parse_string(literal: &str) -> Rc<str> {
+    let mut s = String::new();
+    let mut chars = literal[1..literal.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        s.push(match c {
+            '\\' => match chars.next().unwrap() {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '0' => '\0',
+                c => c,
+            },
+            c => c,
+        });
+    }
+    Rc::from(s)
+}
+
+fn parse_macro_args(pair: Pair<Rule>) -> Vec<MacroArg> {
+    pair.into_inner().map(|arg| {
+        let mut rules = arg.into_inner().peekable();
+        let name = rules.next_if(|rule| rule.as_rule() == Rule::identifier)
+            .map(|rule| Identifier::from(rule.as_str()));
+        MacroArg { name, expr: parse_expr(rules.next().unwrap().into_inner()) }
+    }).collect()
+}
 
 pub fn parse_binop(mut pairs: Pairs<Rule>) -> Binop {
     let first_rule = pairs.next().unwrap();
@@ -64,32 +157,45 @@ pub fn parse_binop_rule(first_rule: Pair<'_, Rule>) -> Binop {
         Rule::LOWER => Binop::Lt,
         Rule::AND => Binop::And,
         Rule::OR => Binop::Or,
+        Rule::RANGE => Binop::Range,
+        Rule::RANGE_INCL => Binop::RangeInclusive,
         _ => unreachable!()
     }
 }
 
+#[allow(dead_code)]
 pub fn parse_boxkind(mut pairs: Pairs<Rule>) -> PtrKind {
     parse_boxkind_rule(pairs.next().unwrap())
 }
 
+#[allow(dead_code)]
 pub fn parse_boxkind_rule(first_rule: Pair<'_, Rule>) -> PtrKind {
-    match first_rule.as_rule() {
-        _ => unreachable!()
-    }
+    unreachable!("parse_boxkind_rule found {:?}", first_rule)
 }
 
 pub fn parse_expr(pairs: Pairs<Rule>) -> Expression {
     PRATT_PARSER
-        .map_primary(|primary| match primary.as_rule() {
+        .map_primary(|primary| spanned(span(&primary), match primary.as_rule() {
             Rule::integer => Expression::Const(ParsedValue::Integer(primary.as_str().parse::<isize>().unwrap())),
             Rule::boolean => Expression::Const(ParsedValue::Boolean(match primary.as_str() {
                 "true" => true,
                 "false" => false,
                 _ => unreachable!()
             })),
+            Rule::string => Expression::Const(ParsedValue::Str(parse_string(primary.as_str()))),
+            Rule::macro_call => {
+                let mut rules = primary.into_inner();
+                let name = Macro::from_name(rules.next().unwrap().as_str()).expect("the grammar is not as expected");
+                Expression::Macro(name, parse_macro_args(rules.next().unwrap()))
+            },
             Rule::lexpr => Expression::ValueAt(parse_lexpr(primary.into_inner())),
             Rule::expr => parse_expr(primary.into_inner()),
             Rule::atom => parse_expr(primary.into_inner()),
+            Rule::paren_expr => {
+                // La portion de l'expression inclut les parenthèses
+                let span = span(&primary);
+                Expression::Spanned(span, Box::new(parse_expr(primary.into_inner().next().unwrap().into_inner())))
+            },
             Rule::conditional_expr => {
                 let mut rules = primary.into_inner();
                 let cond = Box::new(parse_expr(rules.next().unwrap().into_inner()));
@@ -98,7 +204,28 @@ pub fn parse_expr(pairs: Pairs<Rule>) -> Expression {
                 Expression::Conditional{cond, cond_true, cond_false}
             },
             Rule::unit => Expression::Const(ParsedValue::Unit),
-            Rule::identifier => Expression::Identifier(Identifier::from(primary.as_str())),
+            Rule::identifier | Rule::self_kw => Expression::Identifier(Identifier::from(primary.as_str())),
+            Rule::struct_lit => {
+                let mut rules = primary.into_inner();
+                let name = Identifier::from(rules.next().unwrap().as_str());
+                let fields = rules.map(|field| {
+                    let mut rules = field.into_inner();
+                    let id = Identifier::from(rules.next().unwrap().as_str());
+                    (id, parse_expr(rules.next().unwrap().into_inner()))
+                }).collect();
+                Expression::StructLit(name, fields)
+            },
+            Rule::if_expr => parse_if(primary),
+            Rule::closure => {
+                let (is_move, fun) = parse_closure(primary);
+                Expression::Closure{is_move, fun}
+            },
+            Rule::path => {
+                let mut rules = primary.into_inner();
+                let ty = Identifier::from(rules.next().unwrap().as_str());
+                let id = Identifier::from(rules.next().unwrap().as_str());
+                Expression::Path(ty, id)
+            },
             Rule::ptrnew => Expression::NewPtr,
             Rule::deref => {
                 let expr = Box::new(parse_expr(primary.into_inner()));
@@ -109,34 +236,138 @@ pub fn parse_expr(pairs: Pairs<Rule>) -> Expression {
                 Expression::AmpersAnd(expr)
             },
             rule => unreachable!("parse_expr expected atom, found {:?}", rule),
-        })
+        }))
         .map_infix(|lhs, op, rhs| {
+            let span = span_of(&lhs).join(span_of(&rhs));
             let op2 = parse_binop_rule(op);
-            Expression::BinOp(Box::new(lhs), op2, Box::new(rhs))
+            Expression::Spanned(span, Box::new(Expression::BinOp(Box::new(lhs), op2, Box::new(rhs))))
         })
+        .map_postfix(|lhs, op| Expression::Spanned(span_of(&lhs).join(&span(&op)), Box::new(match op.as_rule() {
+            Rule::field => {
+                let id = Identifier::from(op.into_inner().next().unwrap().as_str());
+                Expression::Field(Box::new(lhs), id)
+            },
+            Rule::method_call => {
+                let mut rules = op.into_inner();
+                let id = Identifier::from(rules.next().unwrap().as_str());
+                Expression::MethodCall(Box::new(lhs), id, parse_args(rules.next().unwrap()))
+            },
+            Rule::call => {
+                Expression::Call(Box::new(lhs), parse_args(op.into_inner().next().unwrap()))
+            },
+            rule => unreachable!("parse_expr expected postfix, found {:?}", rule),
+        })))
         .parse(pairs)
 }
 
-fn parse_block(pairs: &mut Pairs<Rule>) -> Result<Vec<Instruction>, ParseError> {
+fn parse_if(pair: Pair<Rule>) -> Expression {
+    let mut rules = pair.into_inner();
+    let cond = Box::new(parse_expr(rules.next().unwrap().into_inner()));
+    let cond_true = Box::new(parse_block(&mut rules.next().unwrap().into_inner()).expect("the grammar is not as expected"));
+    // `else if` : la branche `else` contient une autre expression `if`
+    let cond_false = rules.next().map(|rule| Box::new(match rule.as_rule() {
+        Rule::if_expr => Instruction::Expr(spanned(span(&rule), parse_if(rule))),
+        _ => parse_block(&mut rule.into_inner()).expect("the grammar is not as expected"),
+    }));
+    Expression::If{cond, cond_true, cond_false}
+}
+
+fn parse_closure(pair: Pair<Rule>) -> (bool, Rc<Function>) {
+    let mut rules = pair.into_inner().peekable();
+    let is_move = rules.next_if(|rule| rule.as_rule() == Rule::move_kw).is_some();
+    let params = rules.next().unwrap().into_inner().map(|param| {
+        let mut rules = param.into_inner().peekable();
+        let mutable = rules.next_if(|rule| rule.as_rule() == Rule::mut_kw).is_some();
+        let id = Identifier::from(rules.next().unwrap().as_str());
+        let ty = rules.next().map(parse_type).unwrap_or(Type::Infer);
+        Param { id, mutable, ty }
+    }).collect();
+    let mut ret = Type::Infer;
+    let mut next = rules.next().unwrap();
+    if next.as_rule() == Rule::ret_type {
+        ret = parse_type(next.into_inner().next().unwrap());
+        next = rules.next().unwrap();
+    }
+    let body = match next.as_rule() {
+        Rule::expr => Instruction::Expr(parse_expr(next.into_inner())),
+        _ => parse_block(&mut next.into_inner()).expect("the grammar is not as expected"),
+    };
+    (is_move, Rc::new(Function { attributes: vec![], name: Identifier::from("{closure}"), receiver: None, params, ret, body }))
+}
+
+fn parse_fn(pair: Pair<Rule>) -> Result<Function, ParseError> {
+    let mut rules = pair.into_inner().peekable();
+    let mut attributes = vec![];
+    while let Some(attribute) = rules.next_if(|rule| rule.as_rule() == Rule::attribute) {
+        attributes.push(Identifier::from(attribute.into_inner().next().unwrap().as_str()));
+    }
+    let name = Identifier::from(rules.next().unwrap().as_str());
+    let mut receiver = None;
+    let mut params = vec![];
+    for param in rules.next().unwrap().into_inner() {
+        match param.as_rule() {
+            Rule::receiver => {
+                receiver = Some(match param.into_inner().next().unwrap().as_rule() {
+                    Rule::self_ref_mut => Receiver::RefMut,
+                    Rule::self_ref => Receiver::Ref,
+                    _ => Receiver::Value,
+                })
+            },
+            Rule::param | Rule::mut_param => {
+                let mutable = param.as_rule() == Rule::mut_param;
+                let mut rules = param.into_inner();
+                let id = Identifier::from(rules.next().unwrap().as_str());
+                let ty = parse_type(rules.next().unwrap());
+                params.push(Param { id, mutable, ty })
+            },
+            _ => unreachable!("parse_fn expected parameter, found {:?}", param),
+        }
+    }
+    let mut ret = Type::Unit;
+    let mut next = rules.next().unwrap();
+    if next.as_rule() == Rule::ret_type {
+        ret = parse_type(next.into_inner().next().unwrap());
+        next = rules.next().unwrap();
+    }
+    let body = parse_block(&mut next.into_inner())?;
+    Ok(Function { attributes, name, receiver, params, ret, body })
+}
+
+fn parse_block(pairs: &mut Pairs<Rule>) -> Result<Instruction, ParseError> {
     let first_rule = pairs.next().unwrap();
     let mut res = vec![];
+    let mut tail = false;
     match first_rule.as_rule() {
         Rule::empty_block => { },
         Rule::non_empty_block => {
-            let mut rules = first_rule.into_inner();
-            while let Some(rule) = rules.next(){
-                if rule.as_rule() == Rule::instr {
-                    let instr = parse_instr(&mut rule.into_inner())?;
-                    res.push(instr)
+            tail = true;
+            for rule in first_rule.into_inner() {
+                match rule.as_rule() {
+                    Rule::instr => res.push(parse_instr(&mut rule.into_inner())?),
+                    Rule::trailing_semicolon => tail = false,
+                    _ => unreachable!("parse_block expected instr, found {:?}", rule),
                 }
             }
+            // Une déclaration n'est jamais la valeur du bloc
+            if let Some(Instruction::Let{..} | Instruction::Fn(_) | Instruction::Struct(_) | Instruction::Impl(_, _)) = res.last().map(Instruction::unspanned) {
+                tail = false;
+            }
         },
         _ => unreachable!("parse_block expected instrs, found {:?}", first_rule),
     };
-    Ok(res)
+    Ok(Instruction::Block{instrs: res, tail})
 }
 
 pub fn parse_instr(pairs: &mut Pairs<Rule>) -> Result<Instruction, ParseError> {
+    let span = span(&pairs.peek().unwrap());
+    Ok(match parse_instr_kind(pairs)? {
+        // L'expression porte déjà sa portion
+        instr @ Instruction::Expr(_) => instr,
+        instr => Instruction::Spanned(span, Box::new(instr)),
+    })
+}
+
+fn parse_instr_kind(pairs: &mut Pairs<Rule>) -> Result<Instruction, ParseError> {
     let first_rule = pairs.next().unwrap();
     match first_rule.as_rule() {
         Rule::expr => Ok(Instruction::Expr(parse_expr(first_rule.into_inner()))),
@@ -160,26 +391,47 @@ pub fn parse_instr(pairs: &mut Pairs<Rule>) -> Result<Instruction, ParseError> {
             let expr = parse_expr(rules.next().unwrap().into_inner());
             Ok(Instruction::WriteAt(lexpr, expr))
         },
-        Rule::if_instr => {
-            let mut rules = first_rule.into_inner();
-            let cond = parse_expr(rules.next().unwrap().into_inner());
-            let cond_true = Box::new(Instruction::Block(parse_block(&mut rules.next().unwrap().into_inner())?));
-            let cond_false = Box::new(Instruction::Block(parse_block(&mut rules.next().unwrap().into_inner())?));
-            Ok(Instruction::IfElse{cond, cond_true, cond_false})
-        },
         Rule::while_instr => {
             let mut rules = first_rule.into_inner();
             let cond = parse_expr(rules.next().unwrap().into_inner());
-            let instr = Instruction::Block(parse_block(&mut rules.next().unwrap().into_inner())?);
+            let instr = parse_block(&mut rules.next().unwrap().into_inner())?;
             Ok(Instruction::While(cond, Box::new(instr)))
         },
+        Rule::for_instr => {
+            let mut rules = first_rule.into_inner();
+            let id = Identifier::from(rules.next().unwrap().as_str());
+            let iter = parse_expr(rules.next().unwrap().into_inner());
+            let instr = parse_block(&mut rules.next().unwrap().into_inner())?;
+            Ok(Instruction::For(id, iter, Box::new(instr)))
+        },
+        Rule::break_instr => Ok(Instruction::Break),
+        Rule::continue_instr => Ok(Instruction::Continue),
         Rule::instrs => {
-            Ok(Instruction::Block(parse_block(&mut first_rule.into_inner())?))
+            Ok(parse_block(&mut first_rule.into_inner())?)
         },
         Rule::free_instr => {
             let lexpr = parse_lexpr(first_rule.into_inner());
             Ok(Instruction::Free(lexpr))
         },
+        Rule::fn_decl => {
+            Ok(Instruction::Fn(Rc::new(parse_fn(first_rule)?)))
+        },
+        Rule::struct_decl => {
+            let mut rules = first_rule.into_inner();
+            let name = Identifier::from(rules.next().unwrap().as_str());
+            let fields = rules.map(|field| {
+                let mut rules = field.into_inner();
+                let id = Identifier::from(rules.next().unwrap().as_str());
+                (id, parse_type(rules.next().unwrap()))
+            }).collect();
+            Ok(Instruction::Struct(Rc::new(Structure { name, fields })))
+        },
+        Rule::impl_block => {
+            let mut rules = first_rule.into_inner();
+            let name = Identifier::from(rules.next().unwrap().as_str());
+            let funs: Result<Vec<_>, ParseError> = rules.map(|f| parse_fn(f).map(Rc::new)).collect();
+            Ok(Instruction::Impl(name, funs?))
+        },
         _ => unreachable!("parse_instr expected instr, found {:?}", first_rule),
     }
 }
\ No newline at end of file
@@ -1,7 +1,11 @@
 use std::fmt;
+use std::rc::Rc;
+use crate::identifier::Identifier;
 use crate::memory::Address;
+use crate::parsing::function::{Closure, Function, NativeFunction};
 use crate::parsing::parsedvalue::ParsedValue;
 use crate::r#type::Type;
+use crate::error::EvalError;
 
 
 #[derive(Debug, Clone, PartialEq)]
@@ -9,7 +13,14 @@ pub enum Value {
     Integer(isize),
     Boolean(bool),
     Unit,
+    Str(Rc<str>),
     Pointer(Address),
+    // Intervalle d'entiers, borne supérieure exclue
+    Range(isize, isize),
+    Struct(Identifier, Vec<(Identifier, Value)>),
+    Function(Rc<Function>),
+    Closure(Rc<Closure>),
+    Native(Rc<NativeFunction>),
 }
 
 impl fmt::Display for Value {
@@ -19,7 +30,16 @@ impl fmt::Display for Value {
             Value::Integer(i) => write!(f, "{}", i),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::Unit => write!(f, "()"),
+            Value::Str(s) => write!(f, "{}", s),
             Value::Pointer(a) => write!(f, "{}", a),
+            Value::Range(start, end) => write!(f, "{}..{}", start, end),
+            Value::Struct(name, fields) => {
+                let fields: Vec<String> = fields.iter().map(|(id, v)| format!("{}: {}", id, v)).collect();
+                write!(f, "{} {{ {} }}", name, fields.join(", "))
+            }
+            Value::Function(fun) => write!(f, "<fn {}>", fun.name),
+            Value::Closure(_) => write!(f, "<closure>"),
+            Value::Native(native) => write!(f, "<native fn {}>", native.name),
         }
     }
 }
@@ -30,6 +50,7 @@ impl From<ParsedValue> for Value {
             ParsedValue::Integer(i) => Value::Integer(i),
             ParsedValue::Boolean(b) => Value::Boolean(b),
             ParsedValue::Unit => Value::Unit,
+            ParsedValue::Str(s) => Value::Str(s),
         }
     }
 }
@@ -40,12 +61,30 @@ impl From<&Value> for Type {
             Value::Integer(_) => Type::Int,
             Value::Boolean(_) => Type::Bool,
             Value::Unit => Type::Unit,
+            Value::Str(_) => Type::Str,
             Value::Pointer(_) => Type::Address,
+            Value::Range(_, _) => Type::Range,
+            Value::Struct(name, _) => Type::Struct(name.clone()),
+            Value::Function(fun) => fun.get_type(None),
+            Value::Closure(closure) => closure.fun.get_type(None),
+            Value::Native(native) => native.get_type(),
         }
     }
 }
 
 impl Value {
+    // Rendu de `{:?}` : comme l'affichage, avec les chaînes entre guillemets
+    pub fn debug(&self) -> String {
+        match self {
+            Value::Str(s) => format!("{:?}", s),
+            Value::Struct(name, fields) => {
+                let fields: Vec<String> = fields.iter().map(|(id, v)| format!("{}: {}", id, v.debug())).collect();
+                format!("{} {{ {} }}", name, fields.join(", "))
+            }
+            v => v.to_string(),
+        }
+    }
+
     pub fn to_int(&self) -> Result<isize, Type> {
         match self {
             Value::Integer(i) => Ok(*i),
@@ -58,4 +97,41 @@ impl Value {
             _ => Err(Type::from(self)),
         }
     }
+    pub fn to_address(&self) -> Result<Address, Type> {
+        match self {
+            Value::Pointer(a) => Ok(a.clone()),
+            _ => Err(Type::from(self)),
+        }
+    }
+
+    // Lecture du champ `field` d'une structure
+    pub fn get_field(&self, field: &Identifier) -> Result<&Value, EvalError> {
+        match self {
+            Value::Struct(_, fields) => fields.iter()
+                .find(|(id, _)| id == field)
+                .map(|(_, v)| v)
+                .ok_or(EvalError::UnknownField(Type::from(self), field.clone())),
+            _ => Err(EvalError::UnknownField(Type::from(self), field.clone())),
+        }
+    }
+
+    // Écriture en suivant un chemin de champs imbriqués (`p.a.b`)
+    pub fn set_field(&mut self, path: &[Identifier], value: Value) -> Result<(), EvalError> {
+        match path.split_first() {
+            None => {
+                *self = value;
+                Ok(())
+            }
+            Some((field, rest)) => {
+                let t = Type::from(&*self);
+                match self {
+                    Value::Struct(_, fields) => match fields.iter_mut().find(|(id, _)| id == field) {
+                        Some((_, v)) => v.set_field(rest, value),
+                        None => Err(EvalError::UnknownField(t, field.clone())),
+                    },
+                    _ => Err(EvalError::UnknownField(t, field.clone())),
+                }
+            }
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,115 @@
+use crate::error::{Error, EvalError};
+use crate::identifier::Identifier;
+use crate::interpreter::Interpreter;
+use crate::output;
+use crate::parsing::expression::Expression;
+use crate::parsing::instruction::Instruction;
+use crate::parsing::program::Program;
+use std::io::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+// Fonctions `#[test]` d'un programme, exécutées chacune dans un interpréteur neuf,
+// où seules les déclarations (`fn`, `struct`, `impl`) du programme ont été exécutées
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Passed,
+    Failed(EvalError),
+    Panicked(EvalError),
+    // L'interpréteur lui-même s'est arrêté pendant le test, avec ce message
+    Crashed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    pub name: Identifier,
+    pub outcome: Outcome,
+    // Ce que le test a écrit avec `print!` et `println!`
+    pub output: String,
+    pub duration: Duration,
+}
+
+fn is_item(instr: &Instruction) -> bool {
+    matches!(instr.unspanned(), Instruction::Fn(_) | Instruction::Struct(_) | Instruction::Impl(_, _))
+}
+
+pub fn tests(program: &Program) -> Vec<Identifier> {
+    program.0.iter().filter_map(|instr| match instr.unspanned() {
+        Instruction::Fn(fun) if fun.is_test() => Some(fun.name.clone()),
+        _ => None,
+    }).collect()
+}
+
+fn run(program: &Program, name: &Identifier) -> Result<(), EvalError> {
+    let mut interp = Interpreter::new();
+    for instr in program.0.iter().filter(|instr| is_item(instr)) {
+        interp.exec_instr(instr)?;
+    }
+    let call = Expression::Call(Box::new(Expression::Identifier(name.clone())), vec![]);
+    interp.exec_instr(&Instruction::Expr(call))?;
+    // Un test qui laisse des cellules allouées sur le tas échoue
+    match interp.heap().allocated().first() {
+        Some(addr) => Err(EvalError::MemoryLeak(addr.clone())),
+        None => Ok(()),
+    }
+}
+
+pub fn run_test(program: &Program, name: &Identifier) -> TestResult {
+    let start = Instant::now();
+    // Un arrêt de l'interpréteur n'interrompt pas les tests suivants
+    let (res, output) = output::capture(|| panic::catch_unwind(AssertUnwindSafe(|| run(program, name))));
+    let outcome = match res {
+        Ok(Ok(())) => Outcome::Passed,
+        Ok(Err(e @ EvalError::Panic { .. })) => Outcome::Panicked(e),
+        Ok(Err(e)) => Outcome::Failed(e),
+        Err(payload) => Outcome::Crashed(match payload.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => payload.downcast_ref::<String>().cloned().unwrap_or_default(),
+        }),
+    };
+    TestResult { name: name.clone(), outcome, output, duration: start.elapsed() }
+}
+
+// Compte rendu des tests, comme celui de `cargo test` : vrai si tous passent
+pub fn report(program: &Program, out: &mut impl Write) -> bool {
+    let start = Instant::now();
+    let tests = tests(program);
+    writeln!(out, "\nrunning {} test{}", tests.len(), if tests.len() == 1 { "" } else { "s" }).unwrap();
+    let results: Vec<_> = tests.iter().map(|name| {
+        let res = run_test(program, name);
+        let status = match res.outcome {
+            Outcome::Passed => "ok",
+            Outcome::Failed(_) | Outcome::Crashed(_) => "FAILED",
+            Outcome::Panicked(_) => "panicked",
+        };
+        writeln!(out, "test {} ... {} ({:.2?})", res.name, status, res.duration).unwrap();
+        res
+    }).collect();
+    let failures: Vec<_> = results.iter().filter(|res| res.outcome != Outcome::Passed).collect();
+    if !failures.is_empty() {
+        writeln!(out, "\nfailures:\n").unwrap();
+        for res in &failures {
+            writeln!(out, "---- {} ----", res.name).unwrap();
+            if !res.output.is_empty() {
+                write!(out, "{}", res.output).unwrap();
+            }
+            match &res.outcome {
+                Outcome::Failed(e) | Outcome::Panicked(e) => writeln!(out, "{}\n", Error::EvalError(e.clone()).report()).unwrap(),
+                Outcome::Crashed(message) => writeln!(out, "Internal error: {}\n", message).unwrap(),
+                Outcome::Passed => (),
+            }
+        }
+        writeln!(out, "failures:").unwrap();
+        for res in &failures {
+            writeln!(out, "    {}", res.name).unwrap();
+        }
+    }
+    let count = |f: fn(&Outcome) -> bool| results.iter().filter(|res| f(&res.outcome)).count();
+    writeln!(out, "\ntest result: {}. {} passed; {} failed; {} panicked; finished in {:.2}s\n",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        count(|o| *o == Outcome::Passed),
+        count(|o| matches!(o, Outcome::Failed(_) | Outcome::Crashed(_))),
+        count(|o| matches!(o, Outcome::Panicked(_))),
+        start.elapsed().as_secs_f64()).unwrap();
+    failures.is_empty()
+}
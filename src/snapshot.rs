@@ -0,0 +1,316 @@
+use crate::heap::Heap;
+use crate::identifier::Identifier;
+use crate::memory::Address;
+use crate::memorycell::MemoryCell;
+use crate::namespace::NameSpace;
+use crate::namespacestack::NameSpaceStack;
+use crate::parser::Parse;
+use crate::parsing::expression::Expression;
+use crate::parsing::function::{Capture, Closure, Function};
+use crate::parsing::instruction::Instruction;
+use crate::parsing::value::Value;
+use serde_json::{json, Value as Json};
+use std::rc::Rc;
+
+// Sauvegarde de l'état complet d'une session au format JSON : les NameSpace avec la mutabilité
+// et la valeur de chaque variable, les structures et leurs méthodes, et les cellules du tas.
+// Les fonctions sont enregistrées par leur texte source, une seule fois chacune : deux variables
+// liées à la même fonction le restent après la restauration.
+pub const FORMAT: &str = "microrust-state";
+pub const VERSION: u64 = 1;
+
+struct Saver<'a> {
+    nss: &'a NameSpaceStack,
+    functions: Vec<Rc<Function>>,
+}
+
+impl Saver<'_> {
+    fn function(&mut self, fun: &Rc<Function>) -> usize {
+        match self.functions.iter().position(|f| Rc::ptr_eq(f, fun)) {
+            Some(index) => index,
+            None => {
+                self.functions.push(fun.clone());
+                self.functions.len() - 1
+            }
+        }
+    }
+
+    fn address(addr: &Address) -> Json {
+        match addr {
+            Address::StackAddress(index, id) => json!({"stack": [index, id.to_string()]}),
+            Address::HeapAddress(offset) => json!({"heap": offset}),
+        }
+    }
+
+    fn value(&mut self, value: &Value) -> Result<Json, String> {
+        Ok(match value {
+            Value::Integer(i) => json!({"int": i}),
+            Value::Boolean(b) => json!({"bool": b}),
+            Value::Unit => json!({"unit": null}),
+            Value::Str(s) => json!({"str": s.as_ref()}),
+            Value::Pointer(addr) => json!({"ptr": Self::address(addr)}),
+            Value::Range(start, end) => json!({"range": [start, end]}),
+            Value::Struct(name, fields) => {
+                let fields = fields.iter()
+                    .map(|(id, v)| Ok(json!([id.to_string(), self.value(v)?])))
+                    .collect::<Result<Vec<_>, String>>()?;
+                json!({"struct": {"name": name.to_string(), "fields": fields}})
+            },
+            Value::Function(fun) => json!({"fn": self.function(fun)}),
+            Value::Closure(closure) => {
+                let source = Expression::Closure { is_move: false, fun: closure.fun.clone() }.to_string();
+                let mut captures = vec![];
+                for (id, capture) in &closure.captures {
+                    captures.push(match capture {
+                        Capture::ByValue(v) => json!({"id": id.to_string(), "value": self.value(v)?}),
+                        // Le NameSpace visé est retrouvé par son indice ; s'il a été dépilé, la référence reste pendante
+                        Capture::ByRef(addr, ns_id) => {
+                            let index = self.nss.namespaces().iter().position(|ns| ns.id() == *ns_id);
+                            json!({"id": id.to_string(), "ref": Self::address(addr), "namespace": index})
+                        },
+                    });
+                }
+                json!({"closure": {"source": source, "captures": captures}})
+            },
+            Value::Native(native) => return Err(format!("native function `{}` cannot be saved", native.name)),
+        })
+    }
+
+    // Une cellule libérée est `null`, une cellule allouée mais jamais écrite a une valeur `null`
+    fn cell(&mut self, cell: &MemoryCell) -> Result<Json, String> {
+        if let MemoryCell::NotAllocated = cell {
+            return Ok(Json::Null);
+        }
+        let value = match cell.get_value() {
+            Ok(v) => self.value(v)?,
+            Err(_) => Json::Null,
+        };
+        Ok(json!({"mutable": cell.is_mutable(), "value": value}))
+    }
+}
+
+pub fn save(nss: &NameSpaceStack, heap: &Heap) -> Result<String, String> {
+    if !nss.frames().is_empty() {
+        return Err("the state cannot be saved during a function call".to_string());
+    }
+    let mut saver = Saver { nss, functions: vec![] };
+    let mut namespaces = vec![];
+    for ns in nss.namespaces() {
+        let mut cells = vec![];
+        for (id, cell) in ns.cells() {
+            cells.push(json!({"id": id.to_string(), "cell": saver.cell(cell)?}));
+        }
+        namespaces.push(Json::Array(cells));
+    }
+    let cells = heap.cells().iter().map(|cell| saver.cell(cell)).collect::<Result<Vec<_>, String>>()?;
+    let structs: Vec<String> = nss.structs().iter().map(|s| s.to_string()).collect();
+    let methods: Vec<Json> = nss.methods().into_iter()
+        .map(|(ty, funs)| json!([ty.to_string(), funs.iter().map(|f| saver.function(f)).collect::<Vec<_>>()]))
+        .collect();
+    let functions: Vec<String> = saver.functions.iter().map(|f| f.to_string()).collect();
+    let state = json!({
+        "format": FORMAT,
+        "version": VERSION,
+        "functions": functions,
+        "structs": structs,
+        "methods": methods,
+        "namespaces": namespaces,
+        "heap": cells,
+    });
+    Ok(serde_json::to_string_pretty(&state).unwrap())
+}
+
+struct Restorer {
+    functions: Vec<Rc<Function>>,
+    // Identifiants des NameSpace recréés, dans l'ordre de la pile
+    ns_ids: Vec<usize>,
+}
+
+fn field<'a>(json: &'a Json, key: &str) -> Result<&'a Json, String> {
+    json.get(key).ok_or(format!("missing `{}`", key))
+}
+
+fn array<'a>(json: &'a Json, key: &str) -> Result<&'a Vec<Json>, String> {
+    field(json, key)?.as_array().ok_or(format!("`{}` is not an array", key))
+}
+
+fn string(json: &Json) -> Result<&str, String> {
+    json.as_str().ok_or(format!("expected a string, found `{}`", json))
+}
+
+fn integer(json: &Json) -> Result<isize, String> {
+    json.as_i64().map(|i| i as isize).ok_or(format!("expected an integer, found `{}`", json))
+}
+
+fn index(json: &Json) -> Result<usize, String> {
+    json.as_u64().map(|i| i as usize).ok_or(format!("expected an index, found `{}`", json))
+}
+
+impl Restorer {
+    fn address(json: &Json) -> Result<Address, String> {
+        if let Some(stack) = json.get("stack") {
+            match stack.as_array().map(Vec::as_slice) {
+                Some([index_, id]) => Ok(Address::StackAddress(index(index_)?, Identifier::from(string(id)?))),
+                _ => Err(format!("invalid address `{}`", json)),
+            }
+        } else {
+            Ok(Address::HeapAddress(index(field(json, "heap")?)?))
+        }
+    }
+
+    fn value(&self, json: &Json) -> Result<Value, String> {
+        let Some((tag, content)) = json.as_object().and_then(|o| o.iter().next()) else {
+            return Err(format!("invalid value `{}`", json));
+        };
+        Ok(match tag.as_str() {
+            "int" => Value::Integer(integer(content)?),
+            "bool" => Value::Boolean(content.as_bool().ok_or(format!("invalid boolean `{}`", content))?),
+            "unit" => Value::Unit,
+            "str" => Value::Str(Rc::from(string(content)?)),
+            "ptr" => Value::Pointer(Self::address(content)?),
+            "range" => match content.as_array().map(Vec::as_slice) {
+                Some([start, end]) => Value::Range(integer(start)?, integer(end)?),
+                _ => return Err(format!("invalid range `{}`", content)),
+            },
+            "struct" => {
+                let mut fields = vec![];
+                for f in array(content, "fields")? {
+                    match f.as_array().map(Vec::as_slice) {
+                        Some([id, v]) => fields.push((Identifier::from(string(id)?), self.value(v)?)),
+                        _ => return Err(format!("invalid field `{}`", f)),
+                    }
+                }
+                Value::Struct(Identifier::from(string(field(content, "name")?)?), fields)
+            },
+            "fn" => Value::Function(self.function(content)?),
+            "closure" => {
+                let source = string(field(content, "source")?)?;
+                let fun = match Expression::parse(source).map_err(|e| format!("invalid closure `{}`: {}", source, e))?.unspanned() {
+                    Expression::Closure { fun, .. } => fun.clone(),
+                    _ => return Err(format!("`{}` is not a closure", source)),
+                };
+                let mut captures = vec![];
+                for capture in array(content, "captures")? {
+                    let id = Identifier::from(string(field(capture, "id")?)?);
+                    let capture = match capture.get("value") {
+                        Some(v) => Capture::ByValue(self.value(v)?),
+                        None => {
+                            let addr = Self::address(field(capture, "ref")?)?;
+                            // Un NameSpace dépilé n'a pas d'indice : aucun NameSpace n'aura cet identifiant
+                            let ns_id = match field(capture, "namespace")? {
+                                Json::Null => usize::MAX,
+                                i => *self.ns_ids.get(index(i)?).ok_or(format!("no namespace {}", i))?,
+                            };
+                            Capture::ByRef(addr, ns_id)
+                        },
+                    };
+                    captures.push((id, capture));
+                }
+                Value::Closure(Rc::new(Closure { fun, captures }))
+            },
+            _ => return Err(format!("unknown value kind `{}`", tag)),
+        })
+    }
+
+    fn function(&self, json: &Json) -> Result<Rc<Function>, String> {
+        let i = index(json)?;
+        self.functions.get(i).cloned().ok_or(format!("no function {}", i))
+    }
+
+    fn cell(&self, json: &Json) -> Result<MemoryCell, String> {
+        match json {
+            Json::Null => Ok(MemoryCell::NotAllocated),
+            _ => {
+                let mutable = field(json, "mutable")?.as_bool().ok_or("`mutable` is not a boolean")?;
+                match field(json, "value")? {
+                    Json::Null => Ok(MemoryCell::uninitialized(mutable)),
+                    value => Ok(MemoryCell::new(mutable, self.value(value)?)),
+                }
+            },
+        }
+    }
+}
+
+fn parse_item(source: &str) -> Result<Instruction, String> {
+    Instruction::parse(source)
+        .map(|instr| instr.unspanned().clone())
+        .map_err(|e| format!("cannot parse `{}`: {}", source, e))
+}
+
+pub fn restore(text: &str) -> Result<(NameSpaceStack, Heap), String> {
+    let state: Json = serde_json::from_str(text).map_err(|e| format!("invalid JSON: {}", e))?;
+    if state.get("format").and_then(Json::as_str) != Some(FORMAT) {
+        return Err("not a µRust state file".to_string());
+    }
+    let version = state.get("version").and_then(Json::as_u64).unwrap_or(0);
+    if version != VERSION {
+        return Err(format!("unsupported version {} (expected {})", version, VERSION));
+    }
+
+    let mut functions = vec![];
+    for source in array(&state, "functions")? {
+        match parse_item(string(source)?)? {
+            Instruction::Fn(fun) => functions.push(fun),
+            _ => return Err(format!("`{}` is not a function", source)),
+        }
+    }
+    let namespaces_json = array(&state, "namespaces")?;
+    let mut namespaces: Vec<NameSpace> = namespaces_json.iter().map(|_| NameSpace::new()).collect();
+    if namespaces.is_empty() {
+        return Err("the global namespace is missing".to_string());
+    }
+    let restorer = Restorer { functions, ns_ids: namespaces.iter().map(NameSpace::id).collect() };
+
+    let mut nss = NameSpaceStack::new();
+    for source in array(&state, "structs")? {
+        match parse_item(string(source)?)? {
+            Instruction::Struct(s) => nss.declare_struct(s).map_err(|e| e.to_string())?,
+            _ => return Err(format!("`{}` is not a structure", source)),
+        }
+    }
+    for methods in array(&state, "methods")? {
+        match methods.as_array().map(Vec::as_slice) {
+            Some([ty, funs]) => {
+                let ty = Identifier::from(string(ty)?);
+                for fun in funs.as_array().ok_or(format!("invalid methods `{}`", funs))? {
+                    nss.declare_method(&ty, restorer.function(fun)?).map_err(|e| e.to_string())?;
+                }
+            },
+            _ => return Err(format!("invalid methods `{}`", methods)),
+        }
+    }
+    for (ns, cells) in namespaces.iter_mut().zip(namespaces_json) {
+        for cell in cells.as_array().ok_or(format!("invalid namespace `{}`", cells))? {
+            let id = Identifier::from(string(field(cell, "id")?)?);
+            let cell = restorer.cell(field(cell, "cell")?)?;
+            let value = cell.get_value().map_err(|_| format!("variable `{}` has no value", id))?;
+            ns.declare(&id, cell.is_mutable(), value.clone()).map_err(|e| e.to_string())?;
+        }
+    }
+    for ns in namespaces {
+        nss.push(ns);
+    }
+    let cells = array(&state, "heap")?.iter().map(|cell| restorer.cell(cell)).collect::<Result<Vec<_>, String>>()?;
+    Ok((nss, Heap::from_cells(cells)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cellules du tas, libérées ou non
+    #[test]
+    fn test_heap() {
+        let mut heap = Heap::new();
+        let cell = heap.malloc(true, Value::Integer(5));
+        heap.malloc(false, Value::Pointer(cell.clone()));
+        heap.malloc(false, Value::Unit);
+        heap.alloc();
+        heap.free(&cell).unwrap();
+        let mut nss = NameSpaceStack::new();
+        nss.push(NameSpace::new());
+        let state = save(&nss, &heap).unwrap();
+        let (_, restored) = restore(&state).unwrap();
+        assert_eq!(restored.to_string(), heap.to_string());
+    }
+}
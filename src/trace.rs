@@ -0,0 +1,60 @@
+use crate::error::EvalError;
+use crate::memory::Address;
+use crate::parsing::value::Value;
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::io::Write;
+
+// Journal de l'évaluation : chaque expression et instruction du texte source, avec son résultat
+// et les cellules écrites, indentée selon la profondeur de la NameSpaceStack
+struct Tracer {
+    out: Box<dyn Write>,
+    // Cellules écrites depuis la dernière entrée du journal
+    writes: Vec<String>,
+}
+
+thread_local! {
+    static TRACE: RefCell<Option<Tracer>> = const { RefCell::new(None) };
+}
+
+pub fn start(out: Box<dyn Write>) {
+    TRACE.with(|t| *t.borrow_mut() = Some(Tracer { out, writes: vec![] }));
+}
+
+pub fn stop() {
+    TRACE.with(|t| t.borrow_mut().take());
+}
+
+pub(crate) fn enabled() -> bool {
+    TRACE.with(|t| t.borrow().is_some())
+}
+
+pub(crate) fn write(addr: &Address, value: &Value) {
+    TRACE.with(|t| if let Some(tracer) = t.borrow_mut().as_mut() {
+        tracer.writes.push(format!("{} := {}", addr, value));
+    });
+}
+
+// Début d'un appel de fonction, avec les paramètres liés
+pub(crate) fn call(name: &dyn Display, depth: usize) {
+    entry(format!("{}call {}", "  ".repeat(depth), name), depth);
+}
+
+fn entry(mut line: String, depth: usize) {
+    TRACE.with(|t| if let Some(tracer) = t.borrow_mut().as_mut() {
+        for write in tracer.writes.drain(..) {
+            line.push_str(&format!("\n{}  {}", "  ".repeat(depth), write));
+        }
+        let _ = writeln!(tracer.out, "{}", line);
+    });
+}
+
+// Les entrées sont écrites après l'évaluation : les sous-expressions précèdent l'expression qui les contient
+pub(crate) fn node(node: &dyn Display, res: Result<&Value, &EvalError>, depth: usize) {
+    let text = node.to_string().split_whitespace().collect::<Vec<_>>().join(" ");
+    let res = match res {
+        Ok(v) => v.to_string(),
+        Err(e) => format!("error: {}", e),
+    };
+    entry(format!("{}{} => {}", "  ".repeat(depth), text, res), depth);
+}
@@ -1,9 +1,24 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::identifier::Identifier;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     Int,
     Bool,
     Unit,
+    Str,
     Address,
+    Range,
+    Struct(Identifier),
+    Function(Vec<Type>, Box<Type>),
+    // Type non annoté d'un paramètre ou du retour d'une closure
+    Infer,
+}
+
+impl Type {
+    // `Infer` est compatible avec tous les types
+    pub fn accepts(&self, other: &Type) -> bool {
+        *self == Type::Infer || self == other
+    }
 }
 
 use std::fmt::{self, Display};
@@ -15,7 +30,14 @@ impl Display for Type {
             Int => write!(f, "isize"),
             Bool => write!(f, "bool"),
             Unit => write!(f, "unit"),
+            Str => write!(f, "String"),
             Address => write!(f, "Ptr"),
+            Range => write!(f, "Range"),
+            Struct(id) => write!(f, "{}", id),
+            Function(params, ret) => {
+                write!(f, "fn({}) -> {}", params.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", "), ret)
+            }
+            Infer => write!(f, "_"),
         }
     }
 }
@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use crate::error::EvalError;
+use crate::identifier::Identifier;
+use crate::namespacestack::NameSpaceStack;
+use crate::parsing::binop::Binop::*;
+use crate::parsing::expression::Expression;
+use crate::parsing::function::{Function, Receiver};
+use crate::parsing::instruction::Instruction;
+use crate::parsing::leftexpression::LeftExpression;
+use crate::parsing::macro_call;
+use crate::parsing::value::Value;
+use crate::r#type::Type;
+
+// Types des identifiants déclarés pendant l'analyse, sans toucher au NameSpaceStack.
+// `Type::Infer` désigne un type que l'analyse ne sait pas déterminer sans évaluer.
+#[derive(Clone)]
+pub struct TypeEnv(Vec<HashMap<Identifier, Type>>);
+
+impl Default for TypeEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TypeEnv {
+    pub fn new() -> Self {
+        TypeEnv(vec![HashMap::new()])
+    }
+
+    pub(crate) fn push(&mut self) {
+        self.0.push(HashMap::new());
+    }
+
+    pub(crate) fn pop(&mut self) {
+        self.0.pop();
+    }
+
+    pub(crate) fn declare(&mut self, id: &Identifier, t: Type) {
+        self.0.last_mut().unwrap().insert(id.clone(), t);
+    }
+
+    fn find(&self, id: &Identifier, nss: &NameSpaceStack) -> Result<Type, EvalError> {
+        for scope in self.0.iter().rev() {
+            if let Some(t) = scope.get(id) {
+                return Ok(t.clone());
+            }
+        }
+        nss.find(id).map(|v| Type::from(&v))
+    }
+}
+
+fn known(t: &Type) -> bool {
+    *t != Type::Infer
+}
+
+fn expect(e: &Expression, expected: Type, env: &mut TypeEnv, nss: &NameSpaceStack) -> Result<(), EvalError> {
+    let t = e.type_of(env, nss)?;
+    if known(&t) && t != expected {
+        return Err(EvalError::TypeMismatch{expression: e.clone(), expected, found: Some(t)});
+    }
+    Ok(())
+}
+
+fn field_type(t: Type, field: &Identifier, nss: &NameSpaceStack) -> Result<Type, EvalError> {
+    match &t {
+        Type::Struct(name) => nss.get_struct(name)?
+            .field_type(field)
+            .cloned()
+            .ok_or(EvalError::UnknownField(t.clone(), field.clone())),
+        // Le type pointé par une adresse n'est pas connu
+        Type::Address | Type::Infer => Ok(Type::Infer),
+        _ => Err(EvalError::UnknownField(t.clone(), field.clone())),
+    }
+}
+
+// Les branches d'un `if` doivent avoir le même type, () s'il n'y a pas d'`else`.
+// Une chaîne de `else if` est typée d'un seul tenant, chaque branche une seule fois.
+fn branches_type(cond_true: &Instruction, cond_false: Option<&Instruction>, env: &mut TypeEnv, nss: &NameSpaceStack) -> Result<Type, EvalError> {
+    let mut branches = vec![cond_true];
+    let mut has_else = false;
+    let mut rest = cond_false;
+    while let Some(instr) = rest {
+        rest = match else_if(instr) {
+            Some((cond, cond_true, cond_false)) => {
+                expect(cond, Type::Bool, env, nss)?;
+                branches.push(cond_true);
+                cond_false
+            }
+            None => {
+                has_else = true;
+                branches.push(instr);
+                None
+            }
+        };
+    }
+    let mut expected = if has_else { Type::Infer } else { Type::Unit };
+    for instr in branches {
+        let t = instr.type_of(env, nss)?;
+        if known(&t) && known(&expected) && t != expected {
+            return Err(if has_else { EvalError::IfBranchMismatch{expected, found: t} } else { EvalError::MissingElse(t) });
+        }
+        if known(&t) {
+            expected = t;
+        }
+    }
+    Ok(expected)
+}
+
+// Le `if` d'une branche `else if`
+fn else_if(instr: &Instruction) -> Option<(&Expression, &Instruction, Option<&Instruction>)> {
+    let mut e = match instr.unspanned() {
+        Instruction::Expr(e) => e,
+        _ => return None,
+    };
+    while let Expression::Spanned(_, inner) = e {
+        e = inner;
+    }
+    match e {
+        Expression::If { cond, cond_true, cond_false } => Some((cond, cond_true, cond_false.as_deref())),
+        _ => None,
+    }
+}
+
+// Vérifie les `if` d'une instruction une fois pour toutes, quand elle est déclarée et avant de l'exécuter,
+// d'après les types déclarés. Les autres erreurs de l'analyse sont laissées à l'évaluation.
+pub fn check_branches(instr: &Instruction, env: &mut TypeEnv, nss: &NameSpaceStack) -> Result<(), EvalError> {
+    match instr {
+        Instruction::Expr(e) | Instruction::WriteAt(_, e) => check_expr(e, env, nss),
+        Instruction::Let { id, expr, .. } => {
+            check_expr(expr, env, nss)?;
+            let t = expr.type_of(env, nss).unwrap_or(Type::Infer);
+            env.declare(id, t);
+            Ok(())
+        }
+        Instruction::Block { instrs, .. } => {
+            env.push();
+            let res = instrs.iter().try_for_each(|instr| check_branches(instr, env, nss));
+            env.pop();
+            res
+        }
+        Instruction::While(cond, body) => {
+            check_expr(cond, env, nss)?;
+            check_branches(body, env, nss)
+        }
+        Instruction::For(id, iter, body) => {
+            check_expr(iter, env, nss)?;
+            env.push();
+            env.declare(id, Type::Int);
+            let res = check_branches(body, env, nss);
+            env.pop();
+            res
+        }
+        Instruction::Break | Instruction::Continue | Instruction::Free(_) | Instruction::Struct(_) => Ok(()),
+        Instruction::Fn(fun) => {
+            env.declare(&fun.name, fun.get_type(None));
+            check_function(fun, None, env, nss)
+        }
+        Instruction::Impl(name, funs) => {
+            let self_type = Type::Struct(name.clone());
+            funs.iter().try_for_each(|fun| check_function(fun, Some(&self_type), env, nss))
+        }
+        Instruction::Spanned(span, instr) => check_branches(instr, env, nss).map_err(|err| err.at(span)),
+    }
+}
+
+// Le corps est vérifié avec les types déclarés des paramètres
+fn check_function(fun: &Function, self_type: Option<&Type>, env: &mut TypeEnv, nss: &NameSpaceStack) -> Result<(), EvalError> {
+    env.push();
+    match (fun.receiver, self_type) {
+        (Some(Receiver::Value), Some(t)) => env.declare(&Identifier::from("self"), t.clone()),
+        (Some(_), _) => env.declare(&Identifier::from("self"), Type::Address),
+        _ => (),
+    }
+    for param in &fun.params {
+        env.declare(&param.id, param.ty.clone());
+    }
+    let res = check_branches(&fun.body, env, nss);
+    env.pop();
+    res
+}
+
+fn check_expr(e: &Expression, env: &mut TypeEnv, nss: &NameSpaceStack) -> Result<(), EvalError> {
+    use Expression::*;
+    match e {
+        Const(_) | Identifier(_) | NewPtr | ValueAt(_) | Path(_, _) => Ok(()),
+        BinOp(e1, _, e2) => {
+            check_expr(e1, env, nss)?;
+            check_expr(e2, env, nss)
+        }
+        Conditional { cond, cond_true, cond_false } => {
+            check_expr(cond, env, nss)?;
+            check_expr(cond_true, env, nss)?;
+            check_expr(cond_false, env, nss)
+        }
+        Deref(e) | AmpersAnd(e) | Field(e, _) => check_expr(e, env, nss),
+        StructLit(_, fields) => fields.iter().try_for_each(|(_, e)| check_expr(e, env, nss)),
+        Call(callee, args) | MethodCall(callee, _, args) => {
+            check_expr(callee, env, nss)?;
+            args.iter().try_for_each(|arg| check_expr(arg, env, nss))
+        }
+        Closure { fun, .. } => check_function(fun, None, env, nss),
+        If { cond, cond_true, cond_false } => {
+            // Les branches d'une chaîne de `else if` sont vérifiées ici, puis typées ensemble
+            check_expr(cond, env, nss)?;
+            check_branches(cond_true, env, nss)?;
+            let mut rest = cond_false.as_deref();
+            while let Some(instr) = rest {
+                match else_if(instr) {
+                    Some((cond, cond_true, cond_false)) => {
+                        check_expr(cond, env, nss)?;
+                        check_branches(cond_true, env, nss)?;
+                        rest = cond_false;
+                    }
+                    None => {
+                        check_branches(instr, env, nss)?;
+                        rest = None;
+                    }
+                }
+            }
+            match branches_type(cond_true, cond_false.as_deref(), env, nss) {
+                Err(err @ (EvalError::IfBranchMismatch { .. } | EvalError::MissingElse(_))) => Err(err),
+                _ => Ok(()),
+            }
+        }
+        Macro(_, args) => args.iter().try_for_each(|arg| check_expr(&arg.expr, env, nss)),
+        Spanned(span, e) => check_expr(e, env, nss).map_err(|err| err.at(span)),
+    }
+}
+
+impl Expression {
+    pub fn type_of(&self, env: &mut TypeEnv, nss: &NameSpaceStack) -> Result<Type, EvalError> {
+        use Expression::*;
+        match self {
+            Const(v) => Ok(Type::from(&Value::from(v.clone()))),
+            Identifier(id) | ValueAt(LeftExpression::Identifier(id)) => env.find(id, nss),
+            ValueAt(LeftExpression::Star(_)) => Ok(Type::Infer),
+            ValueAt(LeftExpression::Field(lexpr, field)) => {
+                let t = ValueAt(*lexpr.clone()).type_of(env, nss)?;
+                field_type(t, field, nss)
+            }
+            NewPtr | AmpersAnd(_) => Ok(Type::Address),
+            Deref(_) => Ok(Type::Infer),
+            BinOp(e1, op, e2) => match op {
+                Add | Sub | Mul | Div | Mod => {
+                    expect(e1, Type::Int, env, nss)?;
+                    expect(e2, Type::Int, env, nss)?;
+                    Ok(Type::Int)
+                }
+                Leq | Geq | Lt | Gt => {
+                    expect(e1, Type::Int, env, nss)?;
+                    expect(e2, Type::Int, env, nss)?;
+                    Ok(Type::Bool)
+                }
+                Eq | Neq => {
+                    let t1 = e1.type_of(env, nss)?;
+                    if known(&t1) {
+                        expect(e2, t1, env, nss)?;
+                    }
+                    Ok(Type::Bool)
+                }
+                And | Or => {
+                    expect(e1, Type::Bool, env, nss)?;
+                    expect(e2, Type::Bool, env, nss)?;
+                    Ok(Type::Bool)
+                }
+                Range | RangeInclusive => {
+                    expect(e1, Type::Int, env, nss)?;
+                    expect(e2, Type::Int, env, nss)?;
+                    Ok(Type::Range)
+                }
+            },
+            Conditional { cond, cond_true, cond_false } => {
+                expect(cond, Type::Bool, env, nss)?;
+                let t = cond_true.type_of(env, nss)?;
+                if known(&t) {
+                    expect(cond_false, t.clone(), env, nss)?;
+                    return Ok(t);
+                }
+                cond_false.type_of(env, nss)
+            }
+            StructLit(name, _) => {
+                nss.get_struct(name)?;
+                Ok(Type::Struct(name.clone()))
+            }
+            Field(e, field) => {
+                let t = e.type_of(env, nss)?;
+                field_type(t, field, nss)
+            }
+            Path(ty, id) => nss.get_method(ty, id)
+                .map(|fun| fun.get_type(Some(&Type::Struct(ty.clone()))))
+                .ok_or(EvalError::UnknownMethod(Type::Struct(ty.clone()), id.clone())),
+            Call(callee, _) => match callee.type_of(env, nss)? {
+                Type::Function(_, ret) => Ok(*ret),
+                Type::Infer => Ok(Type::Infer),
+                _ => Err(EvalError::NotCallable(*callee.clone())),
+            },
+            MethodCall(recv, name, _) => match recv.type_of(env, nss)? {
+                Type::Struct(s) => nss.get_method(&s, name)
+                    .map(|fun| fun.ret.clone())
+                    .ok_or(EvalError::UnknownMethod(Type::Struct(s.clone()), name.clone())),
+                Type::Address | Type::Infer => Ok(Type::Infer),
+                t => Err(EvalError::UnknownMethod(t, name.clone())),
+            },
+            Closure { fun, .. } => Ok(fun.get_type(None)),
+            If { cond, cond_true, cond_false } => {
+                expect(cond, Type::Bool, env, nss)?;
+                branches_type(cond_true, cond_false.as_deref(), env, nss)
+            }
+            Macro(macro_call::Macro::Format, _) => Ok(Type::Str),
+            Macro(macro_call::Macro::Panic, _) => Ok(Type::Infer),
+            Macro(_, _) => Ok(Type::Unit),
+            Spanned(_, e) => e.type_of(env, nss),
+        }
+    }
+}
+
+impl Instruction {
+    pub fn type_of(&self, env: &mut TypeEnv, nss: &NameSpaceStack) -> Result<Type, EvalError> {
+        match self {
+            Instruction::Expr(e) => e.type_of(env, nss),
+            Instruction::Let { id, expr, .. } => {
+                let t = expr.type_of(env, nss)?;
+                env.declare(id, t);
+                Ok(Type::Unit)
+            }
+            Instruction::Block { instrs, tail } => {
+                env.push();
+                let mut t = Ok(Type::Unit);
+                for instr in instrs {
+                    t = instr.type_of(env, nss);
+                    if t.is_err() {
+                        break;
+                    }
+                }
+                env.pop();
+                match t {
+                    Ok(_) if !tail => Ok(Type::Unit),
+                    t => t,
+                }
+            }
+            // Une affectation vaut (), comme en Rust
+            Instruction::WriteAt(_, e) => {
+                e.type_of(env, nss)?;
+                Ok(Type::Unit)
+            }
+            Instruction::While(_, _) | Instruction::For(_, _, _) | Instruction::Free(_) => Ok(Type::Unit),
+            // `break` et `continue` n'ont pas de valeur
+            Instruction::Break | Instruction::Continue => Ok(Type::Infer),
+            Instruction::Fn(fun) => {
+                let t = fun.get_type(None);
+                env.declare(&fun.name, t.clone());
+                Ok(t)
+            }
+            Instruction::Struct(_) | Instruction::Impl(_, _) => Ok(Type::Unit),
+            Instruction::Spanned(_, instr) => instr.type_of(env, nss),
+        }
+    }
+}
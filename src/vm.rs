@@ -0,0 +1,327 @@
+use crate::bytecode::{Module, NotBool, Op};
+use crate::error::EvalError;
+use crate::eval;
+use crate::identifier::Identifier;
+use crate::output::{self, Stream};
+use crate::parsing::expression::Expression;
+use crate::parsing::macro_call::Macro;
+use crate::parsing::value::Value;
+use crate::r#type::Type;
+use std::rc::Rc;
+
+// Machine à pile qui exécute le code produit par `compiler`.
+// Chaque appel réserve sur la pile ses variables locales, à partir de `base`, sous ses opérandes ;
+// la fonction appelée est juste en dessous de ses arguments, qui deviennent ses premières variables.
+struct Frame {
+    chunk: usize,
+    ip: usize,
+    base: usize,
+}
+
+struct Vm<'a> {
+    module: &'a Module,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    // Mutabilité et valeur de chaque variable globale, une fois déclarée
+    globals: Vec<Option<(bool, Value)>>,
+}
+
+pub fn run(module: &Module) -> Result<Value, EvalError> {
+    let mut vm = Vm {
+        module,
+        stack: vec![Value::Unit; module.chunks[0].locals],
+        frames: vec![Frame { chunk: 0, ip: 0, base: 0 }],
+        globals: vec![None; module.globals.len()],
+    };
+    loop {
+        match vm.step() {
+            Ok(Some(v)) => return Ok(v),
+            Ok(None) => (),
+            Err(err) => return Err(vm.locate(err)),
+        }
+    }
+}
+
+fn int(v: Value) -> isize {
+    // Les opérandes ont été vérifiés par `CheckInt`
+    v.to_int().unwrap()
+}
+
+impl Vm<'_> {
+    // Situe une erreur comme l'évaluateur : par l'instruction en cours de l'appel le plus profond,
+    // ou à défaut par l'appel qui l'a précédé
+    fn locate(&self, mut err: EvalError) -> EvalError {
+        for frame in self.frames.iter().rev() {
+            if let Some(Some(span)) = self.module.chunks[frame.chunk].spans.get(frame.ip.wrapping_sub(1)) {
+                err = err.at(span);
+            }
+        }
+        err
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap()
+    }
+
+    fn push(&mut self, v: Value) {
+        self.stack.push(v);
+    }
+
+    fn top(&self) -> &Value {
+        self.stack.last().unwrap()
+    }
+
+    fn expr(&self, index: usize) -> Expression {
+        self.module.exprs[index].clone()
+    }
+
+    fn global_id(&self, global: usize) -> Identifier {
+        self.module.globals[global].clone()
+    }
+
+    fn jump(&mut self, target: usize) {
+        self.frames.last_mut().unwrap().ip = target;
+    }
+
+    // Exécute une instruction ; renvoie la valeur du programme quand il se termine
+    fn step(&mut self) -> Result<Option<Value>, EvalError> {
+        let frame = self.frames.last_mut().unwrap();
+        let op = self.module.chunks[frame.chunk].code[frame.ip];
+        frame.ip += 1;
+        let base = frame.base;
+        match op {
+            Op::Const(i) => self.push(self.module.constants[i].clone()),
+            Op::Unit => self.push(Value::Unit),
+            Op::Pop => {
+                self.pop();
+            },
+            Op::PopN(n) => self.stack.truncate(self.stack.len() - n),
+            Op::Load(slot) => self.push(self.stack[base + slot].clone()),
+            Op::Store(slot) => self.stack[base + slot] = self.pop(),
+            Op::LoadGlobal(global) => match &self.globals[global] {
+                Some((_, v)) => self.push(v.clone()),
+                None => return Err(EvalError::Undefined(self.global_id(global))),
+            },
+            Op::DeclareGlobal { global, mutable } => {
+                let v = self.pop();
+                if self.globals[global].is_some() {
+                    return Err(EvalError::AlreadyDefined(self.global_id(global)));
+                }
+                self.globals[global] = Some((mutable, v));
+            },
+            Op::SetGlobal(global) => {
+                let v = self.pop();
+                match &mut self.globals[global] {
+                    Some((true, cell)) => *cell = v,
+                    Some((false, _)) => return Err(EvalError::NotMutable(Some(Expression::Identifier(self.global_id(global))))),
+                    None => return Err(EvalError::Undefined(self.global_id(global))),
+                }
+            },
+            Op::CheckInt(expr) => {
+                if self.top().to_int().is_err() {
+                    return Err(EvalError::TypeMismatch{
+                        expression: self.expr(expr),
+                        expected: Type::Int,
+                        found: Some(Type::from(self.top()))});
+                }
+            },
+            Op::Add | Op::Sub | Op::Mul | Op::Div(_) | Op::Mod(_) | Op::Lt | Op::Leq | Op::Gt | Op::Geq | Op::Range { .. } => {
+                let v2 = int(self.pop());
+                let v1 = int(self.pop());
+                self.push(match op {
+                    Op::Add => Value::Integer(v1 + v2),
+                    Op::Sub => Value::Integer(v1 - v2),
+                    Op::Mul => Value::Integer(v1 * v2),
+                    Op::Div(expr) | Op::Mod(expr) if v2 == 0 => return Err(EvalError::DivisionByZero(self.expr(expr))),
+                    Op::Div(_) => Value::Integer(v1 / v2),
+                    Op::Mod(_) => Value::Integer(v1 % v2),
+                    Op::Lt => Value::Boolean(v1 < v2),
+                    Op::Leq => Value::Boolean(v1 <= v2),
+                    Op::Gt => Value::Boolean(v1 > v2),
+                    Op::Geq => Value::Boolean(v1 >= v2),
+                    Op::Range { inclusive } => Value::Range(v1, if inclusive { v2 + 1 } else { v2 }),
+                    _ => unreachable!(),
+                });
+            },
+            Op::Eq { left, right, negate } => {
+                let v2 = self.pop();
+                let v1 = self.pop();
+                let equal = match (v1, v2) {
+                    (Value::Integer(i1), Value::Integer(i2)) => i1 == i2,
+                    (Value::Str(s1), Value::Str(s2)) => s1 == s2,
+                    (Value::Boolean(b1), Value::Boolean(b2)) => b1 == b2,
+                    (Value::Boolean(_), Value::Integer(_)) => return Err(EvalError::TypeMismatch{
+                        expression: self.expr(right),
+                        expected: Type::Bool,
+                        found: Some(Type::Int)}),
+                    (Value::Integer(_), Value::Boolean(_)) => return Err(EvalError::TypeMismatch{
+                        expression: self.expr(left),
+                        expected: Type::Int,
+                        found: Some(Type::Bool)}),
+                    _ => return Err(EvalError::Undefined(Identifier::from(if negate { "Neq" } else { "Eq" }))),
+                };
+                self.push(Value::Boolean(equal != negate));
+            },
+            Op::AndLeft(target) => {
+                if let Ok(false) = self.top().to_bool() {
+                    self.jump(target);
+                }
+            },
+            Op::AndRight => {
+                let v2 = self.pop();
+                let v1 = self.pop();
+                match (v1.to_bool(), v2.to_bool()) {
+                    (Ok(true), Ok(true)) => self.push(Value::Boolean(true)),
+                    (_, Ok(false)) => self.push(Value::Boolean(false)),
+                    _ => return Err(EvalError::Undefined(Identifier::from("And"))),
+                }
+            },
+            Op::OrLeft(target) => {
+                if let Ok(true) = self.top().to_bool() {
+                    self.jump(target);
+                } else {
+                    self.pop();
+                }
+            },
+            Op::OrRight => {
+                let v = self.pop();
+                self.push(Value::Boolean(v.to_bool() == Ok(true)));
+            },
+            Op::Jump(target) => self.jump(target),
+            Op::JumpIfFalse(target, not_bool) => {
+                match self.pop().to_bool() {
+                    Ok(true) => (),
+                    Ok(false) => self.jump(target),
+                    Err(t) => return Err(match not_bool {
+                        NotBool::Mismatch(expr) => EvalError::TypeMismatch{expression: self.expr(expr), expected: Type::Bool, found: Some(t)},
+                        NotBool::Undefined(name) => EvalError::Undefined(Identifier::from(name)),
+                    }),
+                }
+            },
+            Op::ForRange { slot, expr } => match self.pop() {
+                Value::Range(start, end) => {
+                    self.stack[base + slot] = Value::Integer(start);
+                    self.stack[base + slot + 1] = Value::Integer(end);
+                },
+                v => return Err(EvalError::TypeMismatch{
+                    expression: self.expr(expr),
+                    expected: Type::Range,
+                    found: Some(Type::from(&v))}),
+            },
+            Op::ForNext { slot, var, exit } => {
+                let i = int(self.stack[base + slot].clone());
+                if i < int(self.stack[base + slot + 1].clone()) {
+                    self.stack[base + var] = Value::Integer(i);
+                    self.stack[base + slot] = Value::Integer(i + 1);
+                } else {
+                    self.jump(exit);
+                }
+            },
+            Op::Callee { argc, callee } => match self.top() {
+                Value::Function(fun) if fun.params.len() != argc => {
+                    return Err(EvalError::WrongArgumentCount{function: fun.name.clone(), expected: fun.params.len(), found: argc});
+                },
+                Value::Function(_) => (),
+                _ => return Err(EvalError::NotCallable(self.expr(callee))),
+            },
+            Op::CheckArg { index, expr } => {
+                let Value::Function(fun) = &self.stack[self.stack.len() - index - 2] else { unreachable!() };
+                let (expected, found) = (&fun.params[index].ty, Type::from(self.top()));
+                if !expected.accepts(&found) {
+                    return Err(EvalError::TypeMismatch{expression: self.expr(expr), expected: expected.clone(), found: Some(found)});
+                }
+            },
+            Op::Call { argc, .. } => {
+                let base = self.stack.len() - argc;
+                let Value::Function(fun) = &self.stack[base - 1] else { unreachable!() };
+                let chunk = self.module.chunk_of(fun).expect("function not compiled");
+                self.stack.resize(base + self.module.chunks[chunk].locals, Value::Unit);
+                self.frames.push(Frame { chunk, ip: 0, base });
+            },
+            Op::Return => {
+                let v = self.pop();
+                let frame = self.frames.pop().unwrap();
+                if self.frames.is_empty() {
+                    return Ok(Some(v));
+                }
+                // La fonction appelée est retirée avec ses variables
+                self.stack.truncate(frame.base - 1);
+                let fun = self.module.chunks[frame.chunk].fun.as_ref().unwrap();
+                let caller = self.frames.last().unwrap();
+                let Op::Call { call, .. } = self.module.chunks[caller.chunk].code[caller.ip - 1] else { unreachable!() };
+                // Le résultat d'une fonction sans type de retour est ignoré
+                if fun.ret == Type::Unit {
+                    self.push(Value::Unit);
+                } else if fun.ret.accepts(&Type::from(&v)) {
+                    self.push(v);
+                } else {
+                    return Err(EvalError::TypeMismatch{
+                        expression: self.expr(call),
+                        expected: fun.ret.clone(),
+                        found: Some(Type::from(&v))});
+                }
+            },
+            Op::Macro(mac) => self.macro_call(mac)?,
+            Op::Assert { mac, jump } => {
+                let cond = &self.module.exprs[self.module.macros[mac].operands[0]];
+                match self.top().to_bool() {
+                    Ok(true) => {
+                        self.pop();
+                        self.push(Value::Unit);
+                        self.jump(jump);
+                    },
+                    Ok(false) => (),
+                    Err(t) => return Err(EvalError::TypeMismatch{expression: cond.clone(), expected: Type::Bool, found: Some(t)}),
+                }
+            },
+            Op::AssertCmp { mac, jump } => {
+                let call = &self.module.macros[mac];
+                let (left, right) = (&self.stack[self.stack.len() - 2], self.top());
+                if eval::assertion_holds(&call.name, left, right, &self.module.exprs[call.operands[1]])? {
+                    self.stack.truncate(self.stack.len() - 2);
+                    self.push(Value::Unit);
+                    self.jump(jump);
+                }
+            },
+            Op::Fail(i) => return Err(self.module.errors[i].clone()),
+        }
+        Ok(None)
+    }
+
+    // Les valeurs des arguments, puis celles des variables nommées par la chaîne, sont au sommet de la pile,
+    // au-dessus des opérandes d'une assertion qui a échoué
+    fn macro_call(&mut self, mac: usize) -> Result<(), EvalError> {
+        let call = &self.module.macros[mac];
+        let count = call.args.len().saturating_sub(1);
+        let implicit = self.stack.split_off(self.stack.len() - call.implicit.len());
+        let values = self.stack.split_off(self.stack.len() - count);
+        let operands = self.stack.split_off(self.stack.len() - call.operands.len());
+        let mut lookup = |id: &Identifier| match call.implicit.iter().position(|implicit| implicit == id) {
+            Some(i) => Ok(implicit[i].clone()),
+            None => Err(EvalError::Undefined(id.clone())),
+        };
+        let mut message = || eval::format_values(&call.name, &call.args, &values, &mut lookup);
+        match call.name {
+            Macro::Format => {
+                let text = message()?;
+                self.push(Value::Str(Rc::from(text)));
+                return Ok(());
+            },
+            Macro::Print => output::write(Stream::Stdout, &message()?),
+            Macro::Println => output::write(Stream::Stdout, &format!("{}\n", message()?)),
+            Macro::Eprintln => output::write(Stream::Stderr, &format!("{}\n", message()?)),
+            Macro::Panic if call.args.is_empty() => return Err(eval::panic("explicit panic".to_string())),
+            Macro::Panic => return Err(eval::panic(message()?)),
+            Macro::Assert if call.args.is_empty() => {
+                return Err(eval::panic(format!("assertion failed: {}", self.module.exprs[call.operands[0]])));
+            },
+            Macro::Assert => return Err(eval::panic(message()?)),
+            Macro::AssertEq | Macro::AssertNe => {
+                let message = if call.args.is_empty() { None } else { Some(message()?) };
+                return Err(eval::assertion_failed(&call.name, &operands[0], &operands[1], message));
+            },
+        }
+        self.push(Value::Unit);
+        Ok(())
+    }
+}
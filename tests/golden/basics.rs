@@ -0,0 +1,45 @@
+1 + 1
+1 + 2 - 3 * 4 / 5
+let un = 1
+un
+un + 1
+1 / (1 - 1)
+let repete = 0
+let repete = 0
+let pasdefini = 1 / 0
+let pasdefini = toujourspasdefini
+pasdefini
+let x = 0
+{let x = 1; x+1}
+x + 1
+{let tmp = 0}
+tmp
+0 == 0
+1 - 1 != 1 + 1
+true == false
+true == 1
+true != 1
+true == (1 == 1)
+0 < 0
+1 - 1 >= un + 1
+true > false
+true || false && un == 1
+x == 0 || 1 / x == 1
+1 / x == 1 || x == 0
+(un == 1) ? 1 : 2
+(un != 1) ? 1 : 2
+let mut acc = 0
+acc = acc + 1
+acc
+{let mut flag = false; flag = flag || x == 0; flag}
+{let mut mayfreeze = 0; {let mayfreeze = 1; mayfreeze = 2}}
+{let maymelt = 0; {let mut maymelt = 0; maymelt = 1}}
+if (acc == 0) { acc = acc + 1} else { acc = acc - 1}
+acc
+let mut k = 0
+while (k < 4) {k = k + 3}
+k
+let i = 0
+&i
+{let i = 8; &i}
+{let y = 8; &i}
@@ -0,0 +1,17 @@
+// Closures et captures
+let k = 10
+let add = |x| x + k
+add(5)
+let typed = |x: isize| -> isize { x * 2 }
+typed(4)
+let mut counter = 0
+let incr = || { counter = counter + 1 }
+incr()
+counter
+let make = |n: isize| move |x: isize| x + n
+let add3 = make(3)
+add3(4)
+let moved = move || counter
+counter = 100
+moved()
+3(4)
@@ -0,0 +1,12 @@
+// Commandes de la boucle interactive
+let x = 1
+let mut b = true
+struct S { a: isize }
+:type x + 1
+:type b && true
+:type
+:env
+:heap
+:reset
+x
+:nope
@@ -0,0 +1,22 @@
+// Blocs, conditions et boucles
+let mut n = 0
+{ let x = 1; x + 1 }
+{ let x = 1; x + 1; }
+let v = if n == 0 { 10 } else { 20 }
+let w = if n > 0 { 1 } else if n == 0 { 2 } else { 3 }
+if n == 0 { 1 }
+if n == 0 { 1 } else { true }
+for i in 0..5 {
+    if i == 1 { continue };
+    if i == 4 { break };
+    n = n + i
+}
+n
+let mut total = 0
+for i in 1..=3 { total = total + i }
+total
+while total > 0 { total = total - 4 }
+total
+break
+let r = 2..5
+for i in true { }
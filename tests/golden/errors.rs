@@ -0,0 +1,16 @@
+// Erreurs d'analyse et d'évaluation, avec leur position
+let = 1
+1 +
+let x = (1 + 2))
+fn f(d: isize) -> isize {
+    let y = 10;
+    y / d
+}
+f(0)
+f(2)
+let t = true
+t + 1
+undefined_name
+let t = false
+t = true
+/* un commentaire /* imbriqué */ */ 1 // puis la fin
@@ -0,0 +1,17 @@
+// Macros d'affichage, de formatage et d'assertion
+let n = 42
+println!("n = {}", n)
+{ print!("{} ", 1); println!("{}", 2) }
+let s = format!("[{:>5}|{:<5}|{:^5}]", n, "ab", true)
+println!("{s:?} {0:03} {name}", 7, name = "named")
+println!("{{}} {:.2}", "hello")
+println!("{}")
+println!("{}", 1, 2)
+println!(n)
+assert!(n == 42)
+assert!(n == 41, "n vaut {}", n)
+assert_eq!(n, 42)
+assert_eq!(n + 1, 42)
+assert_ne!("a", "a")
+panic!()
+panic!("n = {n}")
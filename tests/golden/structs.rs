@@ -0,0 +1,25 @@
+// Structures et méthodes
+struct Point { x: isize, y: isize }
+impl Point {
+    fn new(x: isize, y: isize) -> Point { Point { x: x, y: y } }
+    fn norm1(&self) -> isize { self.x + self.y }
+    fn shift(&mut self, d: isize) { self.x = self.x + d }
+}
+let p = Point::new(1, 2)
+p.norm1()
+p.x
+p.z
+p.shift(1)
+let mut q = Point { x: 3, y: 4 }
+q.shift(10)
+q
+q.y = 0
+q.norm1()
+Point { x: 1 }
+Point { x: 1, y: true }
+Point::norm1(&q)
+q.area()
+fn square(n: isize) -> isize { n * n }
+square(3)
+square(true)
+square(1, 2)